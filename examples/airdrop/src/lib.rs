@@ -208,7 +208,7 @@ impl Airdrop {
     /// ```
     #[payable]
     pub fn multisend(&mut self, transfer_type: TransferType, amount: Option<U128>) {
-        assert!(self.accounts.len() > 0, "No accounts");
+        assert!(!self.accounts.is_empty(), "No accounts");
         let token_amount = amount.unwrap_or(U128::from(0));
         assert!(token_amount.0 > 0, "Nothing to send");
 
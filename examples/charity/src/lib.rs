@@ -78,7 +78,7 @@ impl Donations {
     /// ```
     #[payable]
     pub fn donate(&mut self) {
-        assert!(self.beneficiaries.len() > 0, "No beneficiaries");
+        assert!(!self.beneficiaries.is_empty(), "No beneficiaries");
         assert!(
             env::attached_deposit() > 0,
             "Must include amount to be paid to all beneficiaries"
@@ -37,7 +37,7 @@ impl Counter {
     /// near view counter.YOU.testnet get_num
     /// ```
     pub fn get_num(&self) -> i128 {
-        return self.val;
+        self.val
     }
 
     /// Increment the counter.
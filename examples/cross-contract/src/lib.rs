@@ -1,3 +1,5 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     collections::Vector,
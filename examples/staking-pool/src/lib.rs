@@ -0,0 +1,102 @@
+//! Minimal staking pool, mirroring the staking methods of the core staking pool contract.
+//! Used to test treasury staking locally, so there are no rewards and no unstaking delay.
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::LookupMap,
+    env,
+    json_types::U128,
+    near_bindgen, AccountId, Balance, BorshStorageKey, PanicOnDefault, Promise,
+};
+
+near_sdk::setup_alloc!();
+
+#[derive(BorshStorageKey, BorshSerialize)]
+pub enum StorageKeys {
+    Staked,
+    Unstaked,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct StakingPool {
+    staked: LookupMap<AccountId, Balance>,
+    unstaked: LookupMap<AccountId, Balance>,
+}
+
+#[near_bindgen]
+impl StakingPool {
+    /// ```bash
+    /// near call pool.YOU.testnet new --accountId pool.YOU.testnet
+    /// ```
+    #[init]
+    pub fn new() -> Self {
+        StakingPool {
+            staked: LookupMap::new(StorageKeys::Staked),
+            unstaked: LookupMap::new(StorageKeys::Unstaked),
+        }
+    }
+
+    /// Stakes the attached deposit
+    ///
+    /// ```bash
+    /// near call pool.YOU.testnet deposit_and_stake --accountId YOU.testnet --amount 10
+    /// ```
+    #[payable]
+    pub fn deposit_and_stake(&mut self) {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Deposit must be greater than zero");
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        self.staked.insert(&account_id, &(staked + amount));
+    }
+
+    /// Unstakes an amount, available to withdraw right away
+    ///
+    /// ```bash
+    /// near call pool.YOU.testnet unstake '{"amount": "10000000000000000000000000"}' --accountId YOU.testnet
+    /// ```
+    pub fn unstake(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        let staked = self.staked.get(&account_id).unwrap_or(0);
+        assert!(amount.0 <= staked, "Not enough staked balance");
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        self.staked.insert(&account_id, &(staked - amount.0));
+        self.unstaked.insert(&account_id, &(unstaked + amount.0));
+    }
+
+    /// Withdraws an unstaked amount back to the account
+    ///
+    /// ```bash
+    /// near call pool.YOU.testnet withdraw '{"amount": "10000000000000000000000000"}' --accountId YOU.testnet
+    /// ```
+    pub fn withdraw(&mut self, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let unstaked = self.unstaked.get(&account_id).unwrap_or(0);
+        assert!(amount.0 <= unstaked, "Not enough unstaked balance");
+        self.unstaked.insert(&account_id, &(unstaked - amount.0));
+        Promise::new(account_id).transfer(amount.0)
+    }
+
+    /// ```bash
+    /// near view pool.YOU.testnet get_account_staked_balance '{"account_id": "YOU.testnet"}'
+    /// ```
+    pub fn get_account_staked_balance(&self, account_id: AccountId) -> U128 {
+        U128::from(self.staked.get(&account_id).unwrap_or(0))
+    }
+
+    /// ```bash
+    /// near view pool.YOU.testnet get_account_unstaked_balance '{"account_id": "YOU.testnet"}'
+    /// ```
+    pub fn get_account_unstaked_balance(&self, account_id: AccountId) -> U128 {
+        U128::from(self.unstaked.get(&account_id).unwrap_or(0))
+    }
+
+    /// ```bash
+    /// near view pool.YOU.testnet get_account_total_balance '{"account_id": "YOU.testnet"}'
+    /// ```
+    pub fn get_account_total_balance(&self, account_id: AccountId) -> U128 {
+        U128::from(
+            self.staked.get(&account_id).unwrap_or(0) + self.unstaked.get(&account_id).unwrap_or(0),
+        )
+    }
+}
@@ -24,7 +24,7 @@ impl Views {
     /// near view views.testnet get_interval
     /// ```
     pub fn get_interval() -> u64 {
-        return INTERVAL;
+        INTERVAL
     }
 
     /// Get a boolean that represents underlying logic to execute an action
@@ -39,6 +39,6 @@ impl Views {
         let fixed_block = current_block_ts.saturating_sub(remainder);
 
         // modulo check
-        (fixed_block % (INTERVAL * ONE_MINUTE) == 0, None)
+        (fixed_block.is_multiple_of(INTERVAL * ONE_MINUTE), None)
     }
 }
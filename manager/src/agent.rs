@@ -26,11 +26,52 @@ pub struct Agent {
     // stats
     pub total_tasks_executed: U128,
 
+    // Base rewards for checking slots without a task to execute
+    pub total_empty_slot_rewards: U128,
+
+    // Task calls & trigger views that failed
+    pub total_failed_calls: U128,
+
+    // Lifetime rewards, excluding the storage deposit
+    pub total_earnings: U128,
+
+    // Lifetime rewards withdrawn
+    pub total_withdrawals: U128,
+
     // Holds slot number of a missed slot.
     // If other agents see an agent miss a slot, they store the missed slot number.
     // If agent does a task later, this number is reset to zero.
     // Example data: 1633890060000000000 or 0
     pub last_missed_slot: u128,
+
+    // Paged calc_balances_page run that last summed the agent balance
+    pub balances_run: u64,
+}
+
+/// Lifetime agent stats of the network, see `get_agents_stats`
+/// Kept up to date on every execution & withdrawal, so removed agents stay counted
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AgentsStats {
+    pub total_agents: U64,
+    pub total_tasks_executed: U128,
+    pub total_empty_slot_rewards: U128,
+    pub total_failed_calls: U128,
+    pub total_earnings: U128,
+    pub total_withdrawals: U128,
+}
+
+impl Default for AgentsStats {
+    fn default() -> Self {
+        AgentsStats {
+            total_agents: U64::from(0),
+            total_tasks_executed: U128::from(0),
+            total_empty_slot_rewards: U128::from(0),
+            total_failed_calls: U128::from(0),
+            total_earnings: U128::from(0),
+            total_withdrawals: U128::from(0),
+        }
+    }
 }
 
 #[near_bindgen]
@@ -46,7 +87,7 @@ impl Contract {
     /// ```
     #[payable]
     pub fn register_agent(&mut self, payable_account_id: Option<ValidAccountId>) {
-        assert_eq!(self.paused, false, "Register agent paused");
+        assert!(!self.paused, "Register agent paused");
 
         let deposit: Balance = env::attached_deposit();
         let required_deposit: Balance =
@@ -67,7 +108,7 @@ impl Contract {
 
         let payable_id = payable_account_id
             .map(|a| a.into())
-            .unwrap_or_else(|| env::predecessor_account_id());
+            .unwrap_or_else(env::predecessor_account_id);
 
         let total_agents = self.agent_active_queue.len();
         let agent_status = if total_agents == 0 {
@@ -83,11 +124,16 @@ impl Contract {
             payable_account_id: payable_id,
             balance: U128::from(required_deposit),
             total_tasks_executed: U128::from(0),
+            total_empty_slot_rewards: U128::from(0),
+            total_failed_calls: U128::from(0),
+            total_earnings: U128::from(0),
+            total_withdrawals: U128::from(0),
             last_missed_slot: 0,
+            balances_run: 0,
         };
 
         self.agents.insert(&account, &agent);
-        self.available_balance = self.available_balance.saturating_add(required_deposit);
+        self.add_agent_rewards(&agent, required_deposit);
 
         // If the user deposited more than needed, refund them.
         let refund = deposit - required_deposit;
@@ -103,15 +149,15 @@ impl Contract {
     /// ```
     #[payable]
     pub fn update_agent(&mut self, payable_account_id: Option<ValidAccountId>) {
-        assert_eq!(self.paused, false, "Update agent paused");
+        assert!(!self.paused, "Update agent paused");
         assert_one_yocto();
 
         let account = env::predecessor_account_id();
 
         // check that predecessor agent exists
         if let Some(mut agent) = self.agents.get(&account) {
-            if payable_account_id.is_some() {
-                agent.payable_account_id = payable_account_id.unwrap().into();
+            if let Some(payable_account_id) = payable_account_id {
+                agent.payable_account_id = payable_account_id.into();
                 self.agents.insert(&account, &agent);
             }
         } else {
@@ -119,9 +165,9 @@ impl Contract {
         };
 
         // If the user deposited more than needed, refund them.
+        // NOTE: The kept yocto is not owed to anyone, so it stays as surplus
         let yocto: Balance = 1;
         let refund = env::attached_deposit() - yocto;
-        self.available_balance = self.available_balance.saturating_add(yocto);
         if refund > 0 {
             Promise::new(env::predecessor_account_id()).transfer(refund);
         }
@@ -157,20 +203,30 @@ impl Contract {
                     "No Agent balance beyond the storage balance"
                 );
             }
-            let withdrawal_amount = agent_balance - storage_fee;
+            let withdrawal_amount = agent_balance.saturating_sub(storage_fee);
             agent.balance = U128::from(agent_balance - withdrawal_amount);
+            agent.total_withdrawals =
+                U128::from(agent.total_withdrawals.0.saturating_add(withdrawal_amount));
+            self.agents_stats.total_withdrawals = U128::from(
+                self.agents_stats
+                    .total_withdrawals
+                    .0
+                    .saturating_add(withdrawal_amount),
+            );
+            self.sub_agent_rewards(&agent, withdrawal_amount);
 
             // if this is a full exit, remove agent. Otherwise, update agent
             if let Some(remove) = remove {
                 if remove {
                     self.remove_agent(account);
+                    // Storage balance is kept by the contract, no longer owed to the agent
+                    self.sub_agent_rewards(&agent, agent.balance.0);
                 }
             } else {
                 self.agents.insert(&account, &agent);
             }
 
             log!("Withdrawal of {} has been sent.", withdrawal_amount);
-            self.available_balance = self.available_balance.saturating_sub(withdrawal_amount);
             Promise::new(agent.payable_account_id.to_string()).transfer(withdrawal_amount)
         } else {
             env::panic(b"No Agent")
@@ -182,11 +238,10 @@ impl Contract {
     // TODO: Check the state changes! getting: Smart contract panicked: The collection is an inconsistent state. Did previous smart contract execution terminate unexpectedly?
     #[private]
     pub fn remove_agent(&mut self, account_id: AccountId) {
-        self.agents.remove(&account_id);
         // remove agent from agent_active_queue
         let index = self.agent_active_queue.iter().position(|x| x == account_id);
         if let Some(index) = index {
-            self.agent_active_queue.swap_remove(index as u64);
+            self.swap_remove_queued_agent(true, index as u64);
         }
         // remove agent from agent_pending_queue
         let p_index = self
@@ -194,8 +249,9 @@ impl Contract {
             .iter()
             .position(|x| x == account_id);
         if let Some(p_index) = p_index {
-            self.agent_pending_queue.swap_remove(p_index as u64);
+            self.swap_remove_queued_agent(false, p_index as u64);
         }
+        self.agents.remove(&account_id);
     }
 
     /// Allows an agent to withdraw all rewards, paid to the specified payable account id.
@@ -215,6 +271,19 @@ impl Contract {
     pub fn get_agent(&self, account_id: AccountId) -> Option<Agent> {
         self.agents.get(&account_id)
     }
+
+    /// Gets the lifetime stats of all agents, including removed ones, to evaluate the network profitability
+    /// "total_agents" is the current count of active & pending agents
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_agents_stats
+    /// ```
+    pub fn get_agents_stats(&self) -> AgentsStats {
+        AgentsStats {
+            total_agents: U64::from(self.agent_active_queue.len() + self.agent_pending_queue.len()),
+            ..self.agents_stats.clone()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -226,7 +295,7 @@ mod tests {
 
     const BLOCK_START_BLOCK: u64 = 52_201_040;
     const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
-    const AGENT_REGISTRATION_COST: u128 = 2_260_000_000_000_000_000_000;
+    const AGENT_REGISTRATION_COST: u128 = 2_980_000_000_000_000_000_000;
 
     fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -266,7 +335,12 @@ mod tests {
                 payable_account_id: accounts(1).to_string(),
                 balance: U128::from(AGENT_REGISTRATION_COST),
                 total_tasks_executed: U128::from(0),
+                total_empty_slot_rewards: U128::from(0),
+                total_failed_calls: U128::from(0),
+                total_earnings: U128::from(0),
+                total_withdrawals: U128::from(0),
                 last_missed_slot: 0,
+                balances_run: 0,
             })
         );
     }
@@ -302,7 +376,12 @@ mod tests {
                 payable_account_id: accounts(2).to_string(),
                 balance: U128::from(AGENT_REGISTRATION_COST),
                 total_tasks_executed: U128::from(0),
+                total_empty_slot_rewards: U128::from(0),
+                total_failed_calls: U128::from(0),
+                total_earnings: U128::from(0),
+                total_withdrawals: U128::from(0),
                 last_missed_slot: 0,
+                balances_run: 0,
             })
         );
     }
@@ -332,13 +411,58 @@ mod tests {
         contract.withdraw_task_balance();
     }
 
+    #[test]
+    fn test_agents_stats() {
+        let mut context = get_context(accounts(1));
+        context.attached_deposit(AGENT_REGISTRATION_COST);
+        testing_env!(context.is_view(false).build());
+        let mut contract = Contract::new();
+        contract.register_agent(None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.register_agent(None);
+
+        // An empty slot reward, then withdrawn
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build());
+        contract.proxy_call(None);
+        contract.withdraw_task_balance();
+        let agent = contract.get_agent(accounts(1).to_string()).unwrap();
+        assert_eq!(agent.total_empty_slot_rewards.0, 1);
+        assert_eq!(agent.total_withdrawals.0, AGENT_BASE_FEE);
+
+        // A failed call reward, withdrawn by unregistering
+        let agent = contract.agents.get(&accounts(2).to_string()).unwrap();
+        contract.send_base_agent_reward(accounts(2).to_string(), agent, true);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.unregister_agent();
+
+        // The removed agent stays counted
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.get_agents_stats(),
+            AgentsStats {
+                total_agents: U64::from(1),
+                total_tasks_executed: U128::from(0),
+                total_empty_slot_rewards: U128::from(1),
+                total_failed_calls: U128::from(1),
+                total_earnings: U128::from(AGENT_BASE_FEE * 2),
+                total_withdrawals: U128::from(AGENT_BASE_FEE * 2),
+            }
+        );
+    }
+
     #[test]
     fn agent_storage_check() {
         let context = get_context(accounts(1));
         testing_env!(context.build());
         let contract = Contract::new();
         assert_eq!(
-            226, contract.agent_storage_usage,
+            298, contract.agent_storage_usage,
             "Expected different storage usage for the agent."
         );
     }
@@ -1,9 +1,14 @@
+// Contract methods take their optional settings as flat JSON fields
+#![allow(clippy::too_many_arguments)]
+
 pub use agent::Agent;
+use chrono::{NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 use cron_schedule::Schedule;
 use near_sdk::{
     assert_one_yocto,
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::{LookupMap, TreeMap, UnorderedMap, Vector},
+    collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector},
     env,
     json_types::{Base64VecU8, ValidAccountId, U128, U64},
     log, near_bindgen,
@@ -11,15 +16,25 @@ use near_sdk::{
     serde_json::json,
     AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseResult, StorageUsage,
 };
+pub use owner::BalanceReconciliation;
 use std::str::FromStr;
+pub use tasks::BalanceHook;
+pub use tasks::CatchUpPolicy;
+pub use tasks::Contribution;
 pub use tasks::Task;
 pub use tasks::TaskHumanFriendly;
 pub use triggers::Trigger;
+pub use views::CadencePreview;
+pub use views::TaskCostEstimate;
 
 mod agent;
+mod migrate;
 mod owner;
+mod staking;
 mod storage_impl;
 mod tasks;
+#[cfg(test)]
+mod test_utils;
 mod triggers;
 mod utils;
 mod views;
@@ -30,16 +45,22 @@ near_sdk::setup_alloc!();
 pub const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
 pub const BASE_BALANCE: Balance = ONE_NEAR * 5; // safety overhead
 pub const GAS_BASE_PRICE: Balance = 100_000_000;
+// The network floors its gas price at GAS_BASE_PRICE and only raises it under sustained congestion.
+// 10x leaves agent reports room to follow congestion, while capping what a majority of agents
+// reporting high prices can overcharge tasks. The owner can adjust both bounds.
+pub const GAS_PRICE_CEILING: Balance = GAS_BASE_PRICE * 10;
+pub const GAS_PRICE_SAMPLES: usize = 9; // agent reported gas prices used for the median, 1 per agent
 pub const GAS_BASE_FEE: Gas = 3_000_000_000_000;
 // actual is: 13534954161128, higher in case treemap rebalance
 pub const GAS_FOR_CALLBACK: Gas = 30_000_000_000_000;
 pub const AGENT_BASE_FEE: Balance = 500_000_000_000_000_000_000; // 0.0005 Ⓝ (2000 tasks = 1 Ⓝ)
-pub const STAKE_BALANCE_MIN: u128 = 10 * ONE_NEAR;
 
 // Boundary Definitions
 pub const MAX_BLOCK_TS_RANGE: u64 = 1_000_000_000_000_000_000;
 pub const SLOT_GRANULARITY: u64 = 60_000_000_000; // 60 seconds in nanos
 pub const AGENT_EJECT_THRESHOLD: u128 = 600; // how many slots an agent can miss before being ejected. 10 * 60 = 1hr
+pub const MAX_TASKS_PER_OWNER: u64 = 1_000;
+pub const MAX_TASKS_PER_SLOT: u64 = 100; // more tasks overflow into the next slot
 pub const NANO: u64 = 1_000_000_000;
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -51,6 +72,12 @@ pub enum StorageKeys {
     AgentsPending,
     Triggers,
     TaskOwners,
+    DeniedTargets,
+    AllowedTargets,
+    SlotTasks { slot: u128 },
+    OwnerTasks { account_id_hash: Vec<u8> },
+    TaskContracts,
+    ContractTasks { account_id_hash: Vec<u8> },
 }
 
 #[near_bindgen]
@@ -62,6 +89,7 @@ pub struct Contract {
     paused: bool,
     owner_id: AccountId,
     treasury_id: Option<AccountId>,
+    staking_pool_id: Option<AccountId>,
 
     // Agent management
     agents: LookupMap<AccountId, Agent>,
@@ -75,27 +103,43 @@ pub struct Contract {
     agent_task_ratio: [u64; 2],
     agent_active_index: u64,
     agents_eject_threshold: u128,
+    agents_stats: agent::AgentsStats, // lifetime agent counters, removed agents included
 
     // Basic management
-    slots: TreeMap<u128, Vec<Vec<u8>>>,
+    slots: TreeMap<u128, Vector<Vec<u8>>>, // task hashes per slot & sub-slot
     tasks: UnorderedMap<Vec<u8>, Task>,
-    task_owners: UnorderedMap<AccountId, Vec<Vec<u8>>>,
+    task_owners: UnorderedMap<AccountId, UnorderedSet<Vec<u8>>>,
+    task_contracts: UnorderedMap<AccountId, UnorderedSet<Vec<u8>>>, // task hashes per target contract
     triggers: UnorderedMap<Vec<u8>, Trigger>,
+    max_tasks_per_owner: u64,
+    max_tasks_per_slot: u64,
+
+    // Targets tasks & triggers can call, as "contract_id" or "contract_id::function_id"
+    denied_targets: UnorderedSet<String>,
+    allowed_targets: UnorderedSet<String>,
+    allowed_targets_only: bool,
 
     // Economics
-    available_balance: Balance, // tasks + rewards balance
-    staked_balance: Balance,
+    task_balance_total: Balance,  // sum of all task balances
+    agent_rewards_total: Balance, // sum of all agent balances, including storage deposits
+    balance_reconciliation: Option<BalanceReconciliation>, // paged calc_balances in progress
+    balances_run: u64,            // latest paged calc_balances run, marks what it summed
+    migration: Option<migrate::Migration>, // paged state migration in progress
+    staked_balance: Balance,      // held by the staking pool, staked or pending withdraw
     agent_fee: Balance,
     gas_price: Balance,
+    gas_price_floor: Balance,
+    gas_price_ceiling: Balance,
+    gas_price_samples: Vec<(AccountId, Balance)>,
     proxy_callback_gas: Gas,
     slot_granularity: u64,
 
     // Storage
     agent_storage_usage: StorageUsage,
     trigger_storage_usage: StorageUsage,
+    task_storage_usage: StorageUsage,
 }
 
-// TODO: Setup state migration for tasks/triggers, including initial storage calculation
 #[near_bindgen]
 impl Contract {
     /// ```bash
@@ -107,30 +151,52 @@ impl Contract {
             paused: false,
             owner_id: env::signer_account_id(),
             treasury_id: None,
+            staking_pool_id: None,
             tasks: UnorderedMap::new(StorageKeys::Tasks),
             task_owners: UnorderedMap::new(StorageKeys::TaskOwners),
+            task_contracts: UnorderedMap::new(StorageKeys::TaskContracts),
             triggers: UnorderedMap::new(StorageKeys::Triggers),
+            max_tasks_per_owner: MAX_TASKS_PER_OWNER,
+            max_tasks_per_slot: MAX_TASKS_PER_SLOT,
+            denied_targets: UnorderedSet::new(StorageKeys::DeniedTargets),
+            allowed_targets: UnorderedSet::new(StorageKeys::AllowedTargets),
+            allowed_targets_only: false,
             agents: LookupMap::new(StorageKeys::Agents),
             agent_active_queue: Vector::new(StorageKeys::AgentsActive),
             agent_pending_queue: Vector::new(StorageKeys::AgentsPending),
             agent_task_ratio: [1, 2],
             agent_active_index: 0,
             agents_eject_threshold: AGENT_EJECT_THRESHOLD,
+            agents_stats: Default::default(),
             slots: TreeMap::new(StorageKeys::Slots),
-            available_balance: 0,
+            task_balance_total: 0,
+            agent_rewards_total: 0,
+            balance_reconciliation: None,
+            balances_run: 0,
+            migration: None,
             staked_balance: 0,
             agent_fee: AGENT_BASE_FEE,
             gas_price: GAS_BASE_PRICE,
+            gas_price_floor: GAS_BASE_PRICE,
+            gas_price_ceiling: GAS_PRICE_CEILING,
+            gas_price_samples: Vec::new(),
             proxy_callback_gas: GAS_FOR_CALLBACK,
             slot_granularity: SLOT_GRANULARITY,
             agent_storage_usage: 0,
             trigger_storage_usage: 0,
+            task_storage_usage: 0,
         };
         this.measure_account_storage_usage();
         this
     }
 
-    /// Measure the storage an agent will take and need to provide
+    /// Balance held on behalf of users: tasks + rewards balance
+    fn available_balance(&self) -> Balance {
+        self.task_balance_total
+            .saturating_add(self.agent_rewards_total)
+    }
+
+    /// Measure the storage an agent, trigger or task will take and need to provide
     fn measure_account_storage_usage(&mut self) {
         let initial_storage_usage = env::storage_usage();
         let max_len_string = "a".repeat(64);
@@ -141,7 +207,12 @@ impl Contract {
             payable_account_id: max_len_string.clone(),
             balance: U128::from(0),
             total_tasks_executed: U128::from(0),
+            total_empty_slot_rewards: U128::from(0),
+            total_failed_calls: U128::from(0),
+            total_earnings: U128::from(0),
+            total_withdrawals: U128::from(0),
             last_missed_slot: 0,
+            balances_run: 0,
         };
         self.agents.insert(&max_len_string, &tmp_agent);
         self.agent_storage_usage = env::storage_usage() - initial_storage_usage;
@@ -161,6 +232,71 @@ impl Contract {
         self.trigger_storage_usage = env::storage_usage() - initial_storage_usage;
         // Remove the temporary entry.
         self.triggers.remove(&tmp_hash);
+
+        // Calc the task storage needs, including its slot & owner list entries
+        // Arguments are charged on top, per byte
+        let initial_storage_usage = env::storage_usage();
+        let tmp_task = Task {
+            owner_id: max_len_string.clone(),
+            contract_id: max_len_string.clone(),
+            function_id: max_len_string.clone(),
+            cadence: max_len_string.clone(),
+            recurring: false,
+            total_deposit: U128::from(0),
+            deposit: U128::from(0),
+            gas: 0,
+            arguments: Base64VecU8::from(vec![]),
+            slot: U128::from(0),
+            priority_tip: U128::from(0),
+            catch_up: CatchUpPolicy::Skip,
+            catch_up_pending: U64::from(0),
+            missed_occurrences: U64::from(0),
+            max_delay: Some(U64::from(0)),
+            timezone: Some(max_len_string.clone()),
+            low_balance_threshold: Some(U128::from(0)),
+            low_balance_hook: Some(BalanceHook {
+                contract_id: max_len_string.clone(),
+                function_id: max_len_string.clone(),
+            }),
+            low_balance_notified: false,
+            contributions: vec![Contribution {
+                account_id: max_len_string.clone(),
+                amount: U128::from(0),
+                storage_deposit: U128::from(0),
+            }],
+            pending_owner_id: Some(max_len_string.clone()),
+            delegates: Vec::new(),
+            paused: false,
+            storage_deposit: U128::from(0),
+            last_executed: U64::from(0),
+            gas_estimate: Some(0),
+            balances_run: 0,
+        };
+        let tmp_hash = env::sha256(max_len_string.as_bytes());
+        self.tasks.insert(&tmp_hash, &tmp_task);
+        let mut tmp_slot_tasks = Vector::new(StorageKeys::SlotTasks { slot: 0 });
+        tmp_slot_tasks.push(&tmp_hash);
+        self.slots.insert(&0, &tmp_slot_tasks);
+        let mut tmp_owner_tasks = UnorderedSet::new(StorageKeys::OwnerTasks {
+            account_id_hash: env::sha256(max_len_string.as_bytes()),
+        });
+        tmp_owner_tasks.insert(&tmp_hash);
+        self.task_owners.insert(&max_len_string, &tmp_owner_tasks);
+        let mut tmp_contract_tasks = UnorderedSet::new(StorageKeys::ContractTasks {
+            account_id_hash: env::sha256(max_len_string.as_bytes()),
+        });
+        tmp_contract_tasks.insert(&tmp_hash);
+        self.task_contracts
+            .insert(&max_len_string, &tmp_contract_tasks);
+        self.task_storage_usage = env::storage_usage() - initial_storage_usage;
+        // Remove the temporary entries.
+        self.tasks.remove(&tmp_hash);
+        tmp_slot_tasks.clear();
+        self.slots.remove(&0);
+        tmp_owner_tasks.clear();
+        self.task_owners.remove(&max_len_string);
+        tmp_contract_tasks.clear();
+        self.task_contracts.remove(&max_len_string);
     }
 
     /// Takes an optional `offset`: the number of seconds to offset from now (current block timestamp)
@@ -189,18 +325,29 @@ impl Contract {
     /// Parse cadence into a schedule
     /// Get next approximate block from a schedule
     /// return slot from the difference of upcoming block and current block
-    fn get_slot_from_cadence(&self, cadence: String) -> u128 {
-        let current_block_ts = env::block_timestamp(); // NANOS
-
+    fn get_slot_from_cadence(&self, cadence: String, timezone: Option<String>) -> u128 {
         // Schedule params
         // NOTE: eventually use TryFrom
         let schedule = Schedule::from_str(&cadence).unwrap();
-        let next_ts = schedule.next_after(&current_block_ts).unwrap();
-        let next_diff = next_ts - current_block_ts;
+        self.get_slot_from_schedule(&schedule, &timezone, env::block_timestamp())
+    }
+
+    /// Get the next slot of a schedule, as if the current block was at "from_ts" (NANOS)
+    fn get_slot_from_schedule(
+        &self,
+        schedule: &Schedule,
+        timezone: &Option<String>,
+        from_ts: u64,
+    ) -> u128 {
+        let next_ts = self.get_next_ts(schedule, timezone, from_ts).unwrap();
+        let next_diff = next_ts - from_ts;
 
         // Get the next slot, based on the timestamp differences
-        let current = self.get_slot_id(None);
-        let next_slot = self.get_slot_id(Some(next_diff));
+        // Protect against extreme future block schedules
+        let round = |ts: u64| u128::from(ts.saturating_sub(ts % self.slot_granularity));
+        let current = round(from_ts);
+        let next_slot =
+            round(from_ts + u64::min(self.slot_granularity + next_diff, MAX_BLOCK_TS_RANGE));
 
         if current == next_slot {
             // Add slot granularity to make sure the minimum next slot is a block within next slot granularity range
@@ -209,6 +356,43 @@ impl Contract {
             next_slot
         }
     }
+
+    /// Get the next timestamp (NANOS) of a schedule after "from_ts"
+    /// With a timezone, the schedule matches the wall clock of that zone, including DST changes
+    /// Returns None once the schedule has no more occurrences
+    fn get_next_ts(
+        &self,
+        schedule: &Schedule,
+        timezone: &Option<String>,
+        from_ts: u64,
+    ) -> Option<u64> {
+        let tz: Tz = match timezone {
+            Some(timezone) => timezone.parse().expect("Timezone invalid"),
+            None => return schedule.next_after(&from_ts),
+        };
+        let to_naive = |ts: u64| {
+            NaiveDateTime::from_timestamp_opt((ts / NANO) as i64, (ts % NANO) as u32).unwrap()
+        };
+
+        // Shift into wall clock time, so the schedule runs against local hours & days
+        let offset = tz
+            .offset_from_utc_datetime(&to_naive(from_ts))
+            .fix()
+            .local_minus_utc();
+        let mut local_ts = (from_ts as i64 + i64::from(offset) * NANO as i64) as u64;
+        loop {
+            local_ts = schedule.next_after(&local_ts)?;
+
+            // Wall clock times skipped by DST never happen, repeated ones run at their first occurrence
+            if let Some(next) = tz.from_local_datetime(&to_naive(local_ts)).earliest() {
+                let next_ts =
+                    next.timestamp() as u64 * NANO + u64::from(next.timestamp_subsec_nanos());
+                if next_ts > from_ts {
+                    return Some(next_ts);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
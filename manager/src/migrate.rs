@@ -0,0 +1,561 @@
+use crate::*;
+use near_sdk::IntoStorageKey;
+
+/// Contract layout of the first deployment, before tasks were indexed by owner, contract & sub-slot
+/// NOTE: Collections are read as the current ones, as only their handles are stored here.
+///       Their values are still in the first deployment layout, converted by `migrate_page`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldContract {
+    paused: bool,
+    owner_id: AccountId,
+    treasury_id: Option<AccountId>,
+    agents: LookupMap<AccountId, Agent>, // values are OldAgent
+    agent_active_queue: Vector<AccountId>,
+    agent_pending_queue: Vector<AccountId>,
+    agent_task_ratio: [u64; 2],
+    agent_active_index: u64,
+    agents_eject_threshold: u128,
+    slots: TreeMap<u128, Vector<Vec<u8>>>, // values are Vec<Vec<u8>>
+    tasks: UnorderedMap<Vec<u8>, Task>,    // values are OldTask
+    task_owners: UnorderedMap<AccountId, UnorderedSet<Vec<u8>>>, // values are Vec<Vec<u8>>
+    triggers: UnorderedMap<Vec<u8>, Trigger>,
+    available_balance: Balance,
+    staked_balance: Balance,
+    agent_fee: Balance,
+    gas_price: Balance,
+    proxy_callback_gas: Gas,
+    slot_granularity: u64,
+    agent_storage_usage: StorageUsage,
+    trigger_storage_usage: StorageUsage,
+}
+
+/// Task layout of the first deployment
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldTask {
+    pub owner_id: AccountId,
+    pub contract_id: AccountId,
+    pub function_id: String,
+    pub cadence: String,
+    pub recurring: bool,
+    pub total_deposit: U128,
+    pub deposit: U128,
+    pub gas: Gas,
+    pub arguments: Base64VecU8,
+}
+
+/// Agent layout of the first deployment
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldAgent {
+    pub status: agent::AgentStatus,
+    pub payable_account_id: AccountId,
+    pub balance: U128,
+    pub total_tasks_executed: U128,
+    pub last_missed_slot: u128,
+}
+
+/// Collections still holding first deployment values, converted in this order
+#[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq)]
+pub enum MigrationStep {
+    TaskOwners,
+    ActiveAgents,
+    PendingAgents,
+    Tasks,
+    Slots,
+    Schedule,
+}
+
+/// Progress of a paged `migrate_page` run
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Migration {
+    step: MigrationStep,
+    // Entries left to convert, from the end so removals never move one behind the cursor
+    cursor: u64,
+    // Last slot converted, slots are converted in ascending order
+    slot_cursor: Option<u128>,
+    // Restored once done, the contract stays paused in the meantime
+    paused: bool,
+    old_agent_storage_usage: StorageUsage,
+    old_available_balance: Balance,
+    agent_storage_top_up: Balance,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts migrating the state of the first deployment to the current layout, only by the contract account.
+    /// Only the contract fields are converted here, the contract is paused until `migrate_page`
+    /// converted every agent, task & slot.
+    ///
+    /// ```bash
+    /// near deploy --wasmFile res/manager.wasm --initFunction migrate --initArgs '{}' --accountId manager_v1.croncat.testnet
+    /// ```
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Must be called by the contract account"
+        );
+        let old: OldContract = env::state_read().expect("No state to migrate");
+        let migration = Migration {
+            step: MigrationStep::TaskOwners,
+            cursor: old.task_owners.len(),
+            slot_cursor: None,
+            paused: old.paused,
+            old_agent_storage_usage: old.agent_storage_usage,
+            old_available_balance: old.available_balance,
+            agent_storage_top_up: 0,
+        };
+
+        Contract {
+            paused: true,
+            owner_id: old.owner_id,
+            treasury_id: old.treasury_id,
+            staking_pool_id: None,
+            agents: old.agents,
+            agent_active_queue: old.agent_active_queue,
+            agent_pending_queue: old.agent_pending_queue,
+            agent_task_ratio: old.agent_task_ratio,
+            agent_active_index: old.agent_active_index,
+            agents_eject_threshold: old.agents_eject_threshold,
+            agents_stats: Default::default(),
+            slots: old.slots,
+            tasks: old.tasks,
+            task_owners: old.task_owners,
+            task_contracts: UnorderedMap::new(StorageKeys::TaskContracts),
+            triggers: old.triggers,
+            max_tasks_per_owner: MAX_TASKS_PER_OWNER,
+            max_tasks_per_slot: MAX_TASKS_PER_SLOT,
+            denied_targets: UnorderedSet::new(StorageKeys::DeniedTargets),
+            allowed_targets: UnorderedSet::new(StorageKeys::AllowedTargets),
+            allowed_targets_only: false,
+            task_balance_total: 0,
+            agent_rewards_total: 0,
+            balance_reconciliation: None,
+            balances_run: 0,
+            migration: Some(migration),
+            staked_balance: old.staked_balance,
+            agent_fee: old.agent_fee,
+            gas_price: old.gas_price,
+            gas_price_floor: old.gas_price,
+            gas_price_ceiling: u128::max(old.gas_price, GAS_PRICE_CEILING),
+            gas_price_samples: Vec::new(),
+            proxy_callback_gas: old.proxy_callback_gas,
+            slot_granularity: old.slot_granularity,
+            agent_storage_usage: old.agent_storage_usage,
+            trigger_storage_usage: old.trigger_storage_usage,
+            task_storage_usage: 0,
+        }
+    }
+
+    /// Converts up to "limit" entries of the state left by `migrate`, returns true once done.
+    /// In order: the owner index is cleared, storage is measured again, agents get their new
+    /// fields & are topped up with the storage fee they never paid, tasks get their new fields &
+    /// indexes, slots get their task vectors, then tasks missing from any slot get scheduled.
+    /// The balance totals are summed along the way.
+    ///
+    /// NOTE: Entries not converted yet fail to load, so calls reaching them fail without changes.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet migrate_page '{"limit": "100"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    #[private]
+    pub fn migrate_page(&mut self, limit: Option<U64>) -> bool {
+        let mut budget = limit.map(|l| l.0).unwrap_or(100);
+        assert!(budget > 0, "Limit must be greater than zero");
+        let mut migration = self.migration.take().expect("No migration in progress");
+
+        while budget > 0 {
+            if migration.step == MigrationStep::Slots {
+                let next_slot = match migration.slot_cursor {
+                    Some(slot) => self.slots.higher(&slot),
+                    None => self.slots.min(),
+                };
+                match next_slot {
+                    Some(slot) => {
+                        self.migrate_slot(slot);
+                        migration.slot_cursor = Some(slot);
+                    }
+                    None => {
+                        migration.step = MigrationStep::Schedule;
+                        migration.cursor = self.tasks.len();
+                    }
+                }
+                budget -= 1;
+                continue;
+            }
+
+            if migration.cursor > 0 {
+                migration.cursor -= 1;
+                let index = migration.cursor;
+                match migration.step {
+                    MigrationStep::TaskOwners => {
+                        if let Some(owner_id) = self.task_owners.keys_as_vector().get_raw(index) {
+                            self.task_owners.remove_raw(&owner_id);
+                        }
+                    }
+                    MigrationStep::ActiveAgents => {
+                        if let Some(agent_id) = self.agent_active_queue.get(index) {
+                            self.migrate_agent(&agent_id, &mut migration);
+                        }
+                    }
+                    MigrationStep::PendingAgents => {
+                        if let Some(agent_id) = self.agent_pending_queue.get(index) {
+                            self.migrate_agent(&agent_id, &mut migration);
+                        }
+                    }
+                    MigrationStep::Tasks => self.migrate_task(index),
+                    MigrationStep::Schedule => self.schedule_migrated_task(index),
+                    MigrationStep::Slots => unreachable!(),
+                }
+                budget -= 1;
+                continue;
+            }
+
+            // Step done, move to the next one
+            match migration.step {
+                MigrationStep::TaskOwners => {
+                    // Owner index entries could not be measured until cleared
+                    self.measure_account_storage_usage();
+                    migration.step = MigrationStep::ActiveAgents;
+                    migration.cursor = self.agent_active_queue.len();
+                }
+                MigrationStep::ActiveAgents => {
+                    migration.step = MigrationStep::PendingAgents;
+                    migration.cursor = self.agent_pending_queue.len();
+                }
+                MigrationStep::PendingAgents => {
+                    migration.step = MigrationStep::Tasks;
+                    migration.cursor = self.tasks.len();
+                }
+                MigrationStep::Tasks => migration.step = MigrationStep::Slots,
+                MigrationStep::Slots => unreachable!(),
+                MigrationStep::Schedule => {
+                    self.paused = migration.paused;
+                    log!(
+                        "Migrated {} tasks & {} agents, topping up {} of agent storage. Holding {} of the {} previously tracked",
+                        self.tasks.len(),
+                        self.agent_active_queue.len() + self.agent_pending_queue.len(),
+                        migration.agent_storage_top_up,
+                        self.available_balance(),
+                        migration.old_available_balance
+                    );
+                    return true;
+                }
+            }
+        }
+
+        log!("Migrating {:?}, {} left", migration.step, migration.cursor);
+        self.migration = Some(migration);
+        false
+    }
+
+    /// Converts an agent to the current layout. Agents registered before paid the storage of
+    /// the first deployment layout, the contract pays the difference so withdrawals stay the same.
+    fn migrate_agent(&mut self, agent_id: &AccountId, migration: &mut Migration) {
+        let raw = match self.agents.remove_raw(&agent_id.try_to_vec().unwrap()) {
+            Some(raw) => raw,
+            None => return,
+        };
+        let old = OldAgent::try_from_slice(&raw).expect("Agent already migrated");
+        let top_up = u128::from(
+            self.agent_storage_usage
+                .saturating_sub(migration.old_agent_storage_usage),
+        ) * env::storage_byte_cost();
+        migration.agent_storage_top_up = migration.agent_storage_top_up.saturating_add(top_up);
+
+        let agent = Agent {
+            status: old.status,
+            payable_account_id: old.payable_account_id,
+            balance: U128::from(old.balance.0.saturating_add(top_up)),
+            total_tasks_executed: old.total_tasks_executed,
+            total_empty_slot_rewards: U128::from(0),
+            total_failed_calls: U128::from(0),
+            total_earnings: U128::from(0),
+            total_withdrawals: U128::from(0),
+            last_missed_slot: old.last_missed_slot,
+            balances_run: 0,
+        };
+        self.agents_stats.total_tasks_executed = U128::from(
+            self.agents_stats
+                .total_tasks_executed
+                .0
+                .saturating_add(agent.total_tasks_executed.0),
+        );
+        self.agents.insert(agent_id, &agent);
+        self.add_agent_rewards(&agent, agent.balance.0);
+    }
+
+    /// Converts the task at "index" to the current layout, its owner deposit becomes its contribution.
+    /// It stays out of any slot until its slot is converted.
+    fn migrate_task(&mut self, index: u64) {
+        let hash = match self.tasks.keys_as_vector().get(index) {
+            Some(hash) => hash,
+            None => return,
+        };
+        let raw = self.tasks.values_as_vector().get_raw(index).unwrap();
+        let old = OldTask::try_from_slice(&raw).expect("Task already migrated");
+
+        let task = Task {
+            contributions: vec![Contribution {
+                account_id: old.owner_id.clone(),
+                amount: old.total_deposit,
+                storage_deposit: U128::from(0),
+            }],
+            owner_id: old.owner_id,
+            contract_id: old.contract_id,
+            function_id: old.function_id,
+            cadence: old.cadence,
+            recurring: old.recurring,
+            total_deposit: old.total_deposit,
+            deposit: old.deposit,
+            gas: old.gas,
+            arguments: old.arguments,
+            slot: U128::from(0),
+            priority_tip: U128::from(0),
+            catch_up: CatchUpPolicy::Skip,
+            catch_up_pending: U64::from(0),
+            missed_occurrences: U64::from(0),
+            max_delay: None,
+            timezone: None,
+            low_balance_threshold: None,
+            low_balance_hook: None,
+            low_balance_notified: false,
+            pending_owner_id: None,
+            delegates: Vec::new(),
+            paused: false,
+            storage_deposit: U128::from(0),
+            last_executed: U64::from(0),
+            gas_estimate: None,
+            balances_run: 0,
+        };
+        self.tasks
+            .insert_raw(&hash.try_to_vec().unwrap(), &task.try_to_vec().unwrap());
+        self.add_owner_task(&task.owner_id, &hash);
+        self.add_contract_task(&task.contract_id, &hash);
+        self.add_task_balance(&task, task.total_deposit.0);
+    }
+
+    /// Converts a slot to its own task vector, keeping the order of its tasks.
+    /// Tasks removed since, or already in another slot, are left out.
+    /// NOTE: The slot may hold more than max_tasks_per_slot, they all run in the same slot anyway.
+    fn migrate_slot(&mut self, slot: u128) {
+        // The tree keeps its keys, only the values change layout
+        let mut slot_values: LookupMap<u128, Vec<Vec<u8>>> =
+            LookupMap::new([StorageKeys::Slots.into_storage_key(), b"v".to_vec()].concat());
+        let task_hashes = slot_values.get(&slot).unwrap_or_default();
+        let mut slot_tasks = Vector::new(StorageKeys::SlotTasks { slot });
+        for hash in task_hashes.iter() {
+            if let Some(mut task) = self.tasks.get(hash).filter(|t| t.slot.0 == 0) {
+                task.slot = U128::from(slot);
+                self.tasks.insert(hash, &task);
+                slot_tasks.push(hash);
+            }
+        }
+        slot_values.insert_raw(
+            &slot.try_to_vec().unwrap(),
+            &slot_tasks.try_to_vec().unwrap(),
+        );
+    }
+
+    /// Schedules the task at "index" to its next cadence slot, if it was missing from any slot
+    fn schedule_migrated_task(&mut self, index: u64) {
+        let hash = match self.tasks.keys_as_vector().get(index) {
+            Some(hash) => hash,
+            None => return,
+        };
+        let mut task = self.tasks.get(&hash).unwrap();
+        if task.slot.0 == 0 {
+            let next_slot = self.get_slot_from_cadence(task.cadence.clone(), task.timezone.clone());
+            task.slot = U128::from(self.push_slot_task(next_slot, &hash));
+            self.tasks.insert(&hash, &task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::json_types::ValidAccountId;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    const BLOCK_START_BLOCK: u64 = 52_201_040;
+    const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
+
+    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .signer_account_pk(b"ed25519:4ZhGmuKTfQn9ZpHCQVRwEr4JnutL8Uu3kArfxEqksfVM".to_vec())
+            .predecessor_account_id(predecessor_account_id)
+            .block_index(BLOCK_START_BLOCK)
+            .block_timestamp(BLOCK_START_TS);
+        builder
+    }
+
+    fn old_task(owner_id: ValidAccountId, function_id: &str) -> OldTask {
+        OldTask {
+            owner_id: owner_id.into(),
+            contract_id: accounts(3).into(),
+            function_id: function_id.to_string(),
+            cadence: "0 0 */1 * * *".to_string(),
+            recurring: true,
+            total_deposit: U128::from(ONE_NEAR),
+            deposit: U128::from(0),
+            gas: 200,
+            arguments: Base64VecU8::from(vec![]),
+        }
+    }
+
+    /// Writes the state of the first deployment: 2 tasks, only one in a slot along with a
+    /// removed one, and 1 agent. Values are written raw in their old layout.
+    fn write_old_state(slot: u128) {
+        let mut old = OldContract {
+            paused: false,
+            owner_id: accounts(1).into(),
+            treasury_id: None,
+            agents: LookupMap::new(StorageKeys::Agents),
+            agent_active_queue: Vector::new(StorageKeys::AgentsActive),
+            agent_pending_queue: Vector::new(StorageKeys::AgentsPending),
+            agent_task_ratio: [1, 2],
+            agent_active_index: 0,
+            agents_eject_threshold: AGENT_EJECT_THRESHOLD,
+            slots: TreeMap::new(StorageKeys::Slots),
+            tasks: UnorderedMap::new(StorageKeys::Tasks),
+            task_owners: UnorderedMap::new(StorageKeys::TaskOwners),
+            triggers: UnorderedMap::new(StorageKeys::Triggers),
+            available_balance: ONE_NEAR * 2,
+            staked_balance: 0,
+            agent_fee: AGENT_BASE_FEE,
+            gas_price: GAS_BASE_PRICE,
+            proxy_callback_gas: GAS_FOR_CALLBACK,
+            slot_granularity: SLOT_GRANULARITY,
+            agent_storage_usage: 226,
+            trigger_storage_usage: 1373,
+        };
+        for (hash, function_id) in [(vec![1u8], "increment"), (vec![2u8], "decrement")].iter() {
+            old.tasks.insert_raw(
+                &hash.try_to_vec().unwrap(),
+                &old_task(accounts(2), function_id).try_to_vec().unwrap(),
+            );
+        }
+        // The slot is added to the tree with an empty vector, then its old value is written
+        old.slots
+            .insert(&slot, &Vector::new(StorageKeys::SlotTasks { slot }));
+        LookupMap::<u128, Vec<Vec<u8>>>::new(
+            [StorageKeys::Slots.into_storage_key(), b"v".to_vec()].concat(),
+        )
+        .insert_raw(
+            &slot.try_to_vec().unwrap(),
+            &vec![vec![1u8], vec![3u8]].try_to_vec().unwrap(),
+        );
+        old.task_owners.insert_raw(
+            &accounts(2).to_string().try_to_vec().unwrap(),
+            &vec![vec![1u8], vec![2u8]].try_to_vec().unwrap(),
+        );
+        old.agents.insert_raw(
+            &accounts(4).to_string().try_to_vec().unwrap(),
+            &OldAgent {
+                status: agent::AgentStatus::Active,
+                payable_account_id: accounts(4).into(),
+                balance: U128::from(2260000000000000000000),
+                total_tasks_executed: U128::from(3),
+                last_missed_slot: 0,
+            }
+            .try_to_vec()
+            .unwrap(),
+        );
+        old.agent_active_queue.push(&accounts(4).to_string());
+        env::state_write(&old);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let slot = u128::from(BLOCK_START_TS - BLOCK_START_TS % SLOT_GRANULARITY);
+        write_old_state(slot);
+        let mut contract = Contract::migrate();
+        assert!(contract.paused);
+
+        // 1 owner, 1 agent, 2 tasks, 1 slot & its end, 2 tasks to schedule, then finishing up
+        let mut pages = 1;
+        while !contract.migrate_page(Some(U64::from(1))) {
+            pages += 1;
+        }
+        assert_eq!(pages, 9);
+        assert!(!contract.paused);
+        assert!(contract.migration.is_none());
+
+        let task = contract.tasks.get(&vec![1]).unwrap();
+        assert_eq!(task.slot.0, slot);
+        assert_eq!(task.contributions[0].account_id, accounts(2).to_string());
+        assert_eq!(task.contributions[0].amount.0, ONE_NEAR);
+        assert_eq!(contract.slots.get(&slot).unwrap().to_vec(), vec![vec![1]]);
+
+        // The task missing from the slots gets scheduled again
+        let task = contract.tasks.get(&vec![2]).unwrap();
+        assert!(task.slot.0 > slot);
+        assert_eq!(
+            contract.slots.get(&task.slot.0).unwrap().to_vec(),
+            vec![vec![2]]
+        );
+        let (stale, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert!(stale.is_empty() && unscheduled.is_empty());
+
+        assert_eq!(
+            contract
+                .get_tasks_by_owner(accounts(2).to_string(), None, None)
+                .len(),
+            2
+        );
+        assert_eq!(
+            contract
+                .get_tasks_filtered(
+                    Some(accounts(3).to_string()),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None
+                )
+                .len(),
+            2
+        );
+
+        // The agent storage fee grew, the contract pays the difference
+        assert_eq!(contract.agent_storage_usage, 298);
+        let agent_balance = 298 * env::storage_byte_cost();
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, agent_balance);
+        assert_eq!(agent.total_tasks_executed.0, 3);
+        assert_eq!(agent.total_earnings.0, 0);
+        assert_eq!(contract.get_agents_stats().total_tasks_executed.0, 3);
+        assert_eq!(
+            contract.get_available_balances(),
+            (U128::from(ONE_NEAR * 2), U128::from(agent_balance))
+        );
+        assert!(contract.task_storage_usage > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be called by the contract account")]
+    fn test_migrate_not_contract() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        write_old_state(0);
+        Contract::migrate();
+    }
+
+    #[test]
+    #[should_panic(expected = "No migration in progress")]
+    fn test_migrate_page_not_started() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.migrate_page(None);
+    }
+}
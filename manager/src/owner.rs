@@ -1,5 +1,21 @@
 use crate::*;
 
+/// Cursor & partial sums of a paged `calc_balances_page` run
+#[derive(BorshDeserialize, BorshSerialize, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceReconciliation {
+    // Next task index (in tasks keys order) to be summed
+    pub task_index: U64,
+
+    // Next agent index in the active queue, then in the pending queue, to be summed
+    pub active_agent_index: U64,
+    pub pending_agent_index: U64,
+
+    // Partial sums so far
+    pub total_task_balance: U128,
+    pub total_reward_balance: U128,
+}
+
 #[near_bindgen]
 impl Contract {
     /// Changes core configurations
@@ -15,6 +31,11 @@ impl Contract {
         agent_task_ratio: Option<Vec<U64>>,
         agents_eject_threshold: Option<U128>,
         treasury_id: Option<AccountId>,
+        staking_pool_id: Option<AccountId>,
+        gas_price_floor: Option<U128>,
+        gas_price_ceiling: Option<U128>,
+        max_tasks_per_owner: Option<U64>,
+        max_tasks_per_slot: Option<U64>,
     ) {
         assert_eq!(
             self.owner_id,
@@ -29,15 +50,42 @@ impl Contract {
         if let Some(treasury_id) = treasury_id {
             self.treasury_id = Some(treasury_id);
         }
+        if let Some(staking_pool_id) = staking_pool_id {
+            // Balance left in the previous pool could no longer be unstaked or withdrawn
+            assert_eq!(
+                self.staked_balance, 0,
+                "Staked balance must be withdrawn before changing staking pool"
+            );
+            self.staking_pool_id = Some(staking_pool_id);
+        }
 
         if let Some(slot_granularity) = slot_granularity {
             self.slot_granularity = slot_granularity;
         }
         if let Some(paused) = paused {
+            assert!(self.migration.is_none(), "Migration in progress");
             self.paused = paused;
         }
         if let Some(gas_price) = gas_price {
+            // Owner override, until agents report new prices
             self.gas_price = gas_price.0;
+            self.gas_price_samples.clear();
+        }
+        if let Some(gas_price_floor) = gas_price_floor {
+            self.gas_price_floor = gas_price_floor.0;
+        }
+        if let Some(gas_price_ceiling) = gas_price_ceiling {
+            self.gas_price_ceiling = gas_price_ceiling.0;
+        }
+        assert!(
+            self.gas_price_floor <= self.gas_price_ceiling,
+            "Gas price floor cannot be above ceiling"
+        );
+        if gas_price_floor.is_some() || gas_price_ceiling.is_some() {
+            self.gas_price = self
+                .gas_price
+                .max(self.gas_price_floor)
+                .min(self.gas_price_ceiling);
         }
         if let Some(proxy_callback_gas) = proxy_callback_gas {
             self.proxy_callback_gas = proxy_callback_gas.0;
@@ -51,33 +99,303 @@ impl Contract {
         if let Some(agents_eject_threshold) = agents_eject_threshold {
             self.agents_eject_threshold = agents_eject_threshold.0;
         }
+        if let Some(max_tasks_per_owner) = max_tasks_per_owner {
+            self.max_tasks_per_owner = max_tasks_per_owner.0;
+        }
+        if let Some(max_tasks_per_slot) = max_tasks_per_slot {
+            assert!(max_tasks_per_slot.0 > 0, "Slots need room for 1 task");
+            self.max_tasks_per_slot = max_tasks_per_slot.0;
+        }
     }
 
     /// Allows admin to calculate internal balances
+    /// Resets the tracked task & reward totals from the stored tasks and agents
     /// Returns surplus and rewards balances
     /// Can be used to measure how much surplus is remaining for staking / etc
     #[private]
     pub fn calc_balances(&mut self) -> (U128, U128) {
-        let base_balance = BASE_BALANCE; // safety overhead
-        let storage_balance = env::storage_byte_cost().saturating_mul(env::storage_usage() as u128);
-
-        // Using storage + threshold as the start for how much balance is required
-        let required_balance = base_balance.saturating_add(storage_balance);
         let mut total_task_balance: Balance = 0;
         let mut total_reward_balance: Balance = 0;
 
         // Loop all tasks and add
         for (_, t) in self.tasks.iter() {
-            total_task_balance = total_task_balance.saturating_add(t.total_deposit.0);
+            total_task_balance = total_task_balance
+                .saturating_add(t.total_deposit.0)
+                .saturating_add(t.storage_deposit.0);
         }
 
-        // Loop all agents rewards and add
-        for a in self.agent_active_queue.iter() {
+        // Loop all agents rewards and add, pending agents hold balances too
+        for a in self
+            .agent_active_queue
+            .iter()
+            .chain(self.agent_pending_queue.iter())
+        {
             if let Some(agent) = self.agents.get(&a) {
                 total_reward_balance = total_reward_balance.saturating_add(agent.balance.0);
             }
         }
 
+        // A full run supersedes any paged run in progress
+        self.balance_reconciliation = None;
+
+        // update internal values
+        self.task_balance_total = total_task_balance;
+        self.agent_rewards_total = total_reward_balance;
+        self.finalize_balances()
+    }
+
+    /// Incremental version of `calc_balances`, for when there are too many tasks for one call.
+    /// Each call sums up to "limit" tasks, then agents with whatever is left of the limit,
+    /// marking them as summed for this run. Balance changes of tasks & agents already summed
+    /// are added to the partial sums as they happen, so the call that completes the cursor
+    /// resets the tracked totals from the sums, logging any difference, and returns surplus
+    /// and rewards balances, otherwise returns None.
+    ///
+    /// NOTE: Removing a task or agent moves the last one into its index, when that one was not
+    /// summed yet and lands behind the cursor it gets summed right away.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet calc_balances_page '{"limit": "500"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    #[private]
+    pub fn calc_balances_page(
+        &mut self,
+        limit: Option<U64>,
+        restart: Option<bool>,
+    ) -> Option<(U128, U128)> {
+        let mut budget = limit.map(|l| l.0).unwrap_or(100);
+        assert!(budget > 0, "Limit must be greater than zero");
+        // Pick up where the previous page stopped, unless starting over
+        let previous = self.balance_reconciliation.take();
+        let mut progress = match previous.filter(|_| !restart.unwrap_or(false)) {
+            Some(progress) => progress,
+            None => {
+                // New run, tasks & agents summed by previous runs need summing again
+                self.balances_run += 1;
+                BalanceReconciliation {
+                    task_index: U64::from(0),
+                    active_agent_index: U64::from(0),
+                    pending_agent_index: U64::from(0),
+                    total_task_balance: U128::from(0),
+                    total_reward_balance: U128::from(0),
+                }
+            }
+        };
+
+        // Loop a page of tasks and add
+        while budget > 0 && progress.task_index.0 < self.tasks.len() {
+            if let Some(task_hash) = self.tasks.keys_as_vector().get(progress.task_index.0) {
+                if let Some(balance) = self.sum_task_balance(&task_hash) {
+                    progress.total_task_balance =
+                        U128::from(progress.total_task_balance.0.saturating_add(balance));
+                }
+            }
+            progress.task_index = U64::from(progress.task_index.0 + 1);
+            budget -= 1;
+        }
+
+        // Loop a page of agents rewards and add, active agents first then pending
+        while budget > 0 && progress.active_agent_index.0 < self.agent_active_queue.len() {
+            if let Some(agent_id) = self.agent_active_queue.get(progress.active_agent_index.0) {
+                if let Some(balance) = self.sum_agent_balance(&agent_id) {
+                    progress.total_reward_balance =
+                        U128::from(progress.total_reward_balance.0.saturating_add(balance));
+                }
+            }
+            progress.active_agent_index = U64::from(progress.active_agent_index.0 + 1);
+            budget -= 1;
+        }
+        while budget > 0 && progress.pending_agent_index.0 < self.agent_pending_queue.len() {
+            if let Some(agent_id) = self.agent_pending_queue.get(progress.pending_agent_index.0) {
+                if let Some(balance) = self.sum_agent_balance(&agent_id) {
+                    progress.total_reward_balance =
+                        U128::from(progress.total_reward_balance.0.saturating_add(balance));
+                }
+            }
+            progress.pending_agent_index = U64::from(progress.pending_agent_index.0 + 1);
+            budget -= 1;
+        }
+
+        let total_agents = self.agent_active_queue.len() + self.agent_pending_queue.len();
+        let agents_summed = progress.active_agent_index.0 + progress.pending_agent_index.0;
+        if progress.task_index.0 < self.tasks.len()
+            || progress.active_agent_index.0 < self.agent_active_queue.len()
+            || progress.pending_agent_index.0 < self.agent_pending_queue.len()
+        {
+            log!(
+                "Reconciled {} of {} tasks, {} of {} agents",
+                progress.task_index.0,
+                self.tasks.len(),
+                agents_summed,
+                total_agents
+            );
+            self.balance_reconciliation = Some(progress);
+            return None;
+        }
+
+        if progress.total_task_balance.0 != self.task_balance_total
+            || progress.total_reward_balance.0 != self.agent_rewards_total
+        {
+            log!(
+                "Summed balances differ from the tracked totals: tasks {} vs {}, rewards {} vs {}",
+                progress.total_task_balance.0,
+                self.task_balance_total,
+                progress.total_reward_balance.0,
+                self.agent_rewards_total
+            );
+        }
+
+        // update internal values
+        self.task_balance_total = progress.total_task_balance.0;
+        self.agent_rewards_total = progress.total_reward_balance.0;
+        Some(self.finalize_balances())
+    }
+
+    /// Marks a task as summed for the current paged run
+    /// Returns its balance, or None if it was already summed
+    fn sum_task_balance(&mut self, task_hash: &Vec<u8>) -> Option<Balance> {
+        let mut task = self.tasks.get(task_hash)?;
+        if task.balances_run == self.balances_run {
+            return None;
+        }
+        task.balances_run = self.balances_run;
+        self.tasks.insert(task_hash, &task);
+        Some(task.total_deposit.0.saturating_add(task.storage_deposit.0))
+    }
+
+    /// Marks an agent as summed for the current paged run
+    /// Returns its balance, or None if it was already summed
+    fn sum_agent_balance(&mut self, agent_id: &AccountId) -> Option<Balance> {
+        let mut agent = self.agents.get(agent_id)?;
+        if agent.balances_run == self.balances_run {
+            return None;
+        }
+        agent.balances_run = self.balances_run;
+        self.agents.insert(agent_id, &agent);
+        Some(agent.balance.0)
+    }
+
+    /// Whether a paged run is in progress & already summed the task or agent with this mark
+    fn is_summed(&self, balances_run: u64) -> bool {
+        self.balance_reconciliation.is_some() && balances_run == self.balances_run
+    }
+
+    /// Adds to the tracked task balances, and to the paged sums once the task was summed
+    pub(crate) fn add_task_balance(&mut self, task: &Task, amount: Balance) {
+        self.task_balance_total = self.task_balance_total.saturating_add(amount);
+        if self.is_summed(task.balances_run) {
+            if let Some(progress) = self.balance_reconciliation.as_mut() {
+                progress.total_task_balance =
+                    U128::from(progress.total_task_balance.0.saturating_add(amount));
+            }
+        }
+    }
+
+    /// Subtracts from the tracked task balances, and from the paged sums once the task was summed
+    pub(crate) fn sub_task_balance(&mut self, task: &Task, amount: Balance) {
+        self.task_balance_total = self.task_balance_total.saturating_sub(amount);
+        if self.is_summed(task.balances_run) {
+            if let Some(progress) = self.balance_reconciliation.as_mut() {
+                progress.total_task_balance =
+                    U128::from(progress.total_task_balance.0.saturating_sub(amount));
+            }
+        }
+    }
+
+    /// Adds to the tracked agent rewards, and to the paged sums once the agent was summed
+    pub(crate) fn add_agent_rewards(&mut self, agent: &Agent, amount: Balance) {
+        self.agent_rewards_total = self.agent_rewards_total.saturating_add(amount);
+        if self.is_summed(agent.balances_run) {
+            if let Some(progress) = self.balance_reconciliation.as_mut() {
+                progress.total_reward_balance =
+                    U128::from(progress.total_reward_balance.0.saturating_add(amount));
+            }
+        }
+    }
+
+    /// Subtracts from the tracked agent rewards, and from the paged sums once the agent was summed
+    pub(crate) fn sub_agent_rewards(&mut self, agent: &Agent, amount: Balance) {
+        self.agent_rewards_total = self.agent_rewards_total.saturating_sub(amount);
+        if self.is_summed(agent.balances_run) {
+            if let Some(progress) = self.balance_reconciliation.as_mut() {
+                progress.total_reward_balance =
+                    U128::from(progress.total_reward_balance.0.saturating_sub(amount));
+            }
+        }
+    }
+
+    /// Removes a task, without a paged run skipping the last task moved into its index
+    pub(crate) fn remove_task_entry(&mut self, task_hash: &Vec<u8>) -> Option<Task> {
+        let task = self.tasks.get(task_hash)?;
+        if self.is_summed(task.balances_run) {
+            let keys = self.tasks.keys_as_vector();
+            if let Some(last_hash) = keys.get(keys.len() - 1) {
+                if let Some(balance) = self.sum_task_balance(&last_hash) {
+                    if let Some(progress) = self.balance_reconciliation.as_mut() {
+                        progress.total_task_balance =
+                            U128::from(progress.total_task_balance.0.saturating_add(balance));
+                    }
+                }
+            }
+        }
+        self.tasks.remove(task_hash);
+        if let Some(progress) = self.balance_reconciliation.as_mut() {
+            progress.task_index = U64::from(u64::min(progress.task_index.0, self.tasks.len()));
+        }
+        Some(task)
+    }
+
+    /// Swap removes an agent from the active or pending queue,
+    /// without a paged run skipping the last agent moved into its index
+    pub(crate) fn swap_remove_queued_agent(&mut self, active: bool, index: u64) -> AccountId {
+        let queue = if active {
+            &self.agent_active_queue
+        } else {
+            &self.agent_pending_queue
+        };
+        let agent_id = queue.get(index).expect("No agent at index");
+        let last_id = queue.get(queue.len() - 1).expect("No agent at index");
+        let removed_summed = self
+            .agents
+            .get(&agent_id)
+            .is_some_and(|agent| self.is_summed(agent.balances_run));
+        if removed_summed {
+            if let Some(balance) = self.sum_agent_balance(&last_id) {
+                if let Some(progress) = self.balance_reconciliation.as_mut() {
+                    progress.total_reward_balance =
+                        U128::from(progress.total_reward_balance.0.saturating_add(balance));
+                }
+            }
+        }
+
+        let queue = if active {
+            &mut self.agent_active_queue
+        } else {
+            &mut self.agent_pending_queue
+        };
+        queue.swap_remove(index);
+        let queue_len = queue.len();
+        if let Some(progress) = self.balance_reconciliation.as_mut() {
+            let cursor = if active {
+                &mut progress.active_agent_index
+            } else {
+                &mut progress.pending_agent_index
+            };
+            *cursor = U64::from(u64::min(cursor.0, queue_len));
+        }
+        agent_id
+    }
+
+    /// Computes the surplus from the tracked task & reward balances
+    /// Returns surplus and rewards balances
+    fn finalize_balances(&self) -> (U128, U128) {
+        let total_task_balance = self.task_balance_total;
+        let total_reward_balance = self.agent_rewards_total;
+        let base_balance = BASE_BALANCE; // safety overhead
+        let storage_balance = env::storage_byte_cost().saturating_mul(env::storage_usage() as u128);
+
+        // Using storage + threshold as the start for how much balance is required
+        let required_balance = base_balance.saturating_add(storage_balance);
         let total_available_balance: Balance =
             total_task_balance.saturating_add(total_reward_balance);
 
@@ -91,9 +409,6 @@ impl Contract {
         );
         log!("Stakeable surplus {}", surplus);
 
-        // update internal values
-        self.available_balance = u128::max(total_available_balance, 0);
-
         // Return surplus value in case we want to trigger staking based off outcome
         (U128::from(surplus), U128::from(total_reward_balance))
     }
@@ -126,6 +441,118 @@ impl Contract {
         Promise::new(account_id).transfer(amount.0)
     }
 
+    /// Repairs the slot index for a page of slots & tasks, only by owner.
+    /// Drops slot entries that do not match a scheduled task, then reschedules
+    /// tasks that went missing from their slot into their next cadence slot.
+    /// Run page by page until `get_slot_inconsistencies` reports nothing, see it for the parameters.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet audit_slots '{"from_slot": "1633759440000000000", "from_index": "0", "limit": "100"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn audit_slots(
+        &mut self,
+        from_slot: Option<U128>,
+        from_index: Option<U64>,
+        limit: Option<U64>,
+    ) -> (U64, U64) {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        let (stale, unscheduled) = self.find_slot_inconsistencies(from_slot, from_index, limit);
+
+        for (slot, task_hash) in stale.iter() {
+            self.remove_slot_task(*slot, task_hash);
+        }
+
+        for task_hash in unscheduled.iter() {
+            if let Some(mut task) = self.tasks.get(task_hash) {
+                let next_slot =
+                    self.get_slot_from_cadence(task.cadence.clone(), task.timezone.clone());
+                task.slot = U128::from(self.push_slot_task(next_slot, task_hash));
+                self.tasks.insert(task_hash, &task);
+            }
+        }
+        log!(
+            "Slot audit removed {} entries, rescheduled {} tasks",
+            stale.len(),
+            unscheduled.len()
+        );
+
+        (
+            U64::from(stale.len() as u64),
+            U64::from(unscheduled.len() as u64),
+        )
+    }
+
+    /// Adds & removes targets tasks and triggers cannot call, only by owner.
+    /// Targets are a "contract_id", or a single method as "contract_id::function_id".
+    /// Tasks calling a denied target exit when they would next execute, refunding their balance.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet update_denied_targets '{"add": ["scam.testnet", "old.testnet::migrate"]}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn update_denied_targets(&mut self, add: Option<Vec<String>>, remove: Option<Vec<String>>) {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        for target in add.unwrap_or_default().iter() {
+            assert_valid_target(target);
+            self.denied_targets.insert(target);
+        }
+        for target in remove.unwrap_or_default().iter() {
+            self.denied_targets.remove(target);
+        }
+    }
+
+    /// Adds & removes targets tasks and triggers can call, only by owner.
+    /// The allow list is only enforced once "allowed_targets_only" is turned on.
+    ///
+    /// Optional Parameters:
+    /// "allowed_targets_only" - Only allow targets on the allow list (that are not also denied).
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet update_allowed_targets '{"add": ["counter.testnet"], "allowed_targets_only": true}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn update_allowed_targets(
+        &mut self,
+        add: Option<Vec<String>>,
+        remove: Option<Vec<String>>,
+        allowed_targets_only: Option<bool>,
+    ) {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        for target in add.unwrap_or_default().iter() {
+            assert_valid_target(target);
+            self.allowed_targets.insert(target);
+        }
+        for target in remove.unwrap_or_default().iter() {
+            self.allowed_targets.remove(target);
+        }
+        if let Some(allowed_targets_only) = allowed_targets_only {
+            self.allowed_targets_only = allowed_targets_only;
+        }
+    }
+
+    /// Checks a contract method against the deny list, and the allow list when enforced
+    pub(crate) fn is_target_allowed(&self, contract_id: &str, function_id: &str) -> bool {
+        let method = format!("{}::{}", contract_id, function_id);
+        if self.denied_targets.contains(&contract_id.to_string())
+            || self.denied_targets.contains(&method)
+        {
+            return false;
+        }
+        !self.allowed_targets_only
+            || self.allowed_targets.contains(&contract_id.to_string())
+            || self.allowed_targets.contains(&method)
+    }
+
     // /// Allows admin to remove slot data, in case a task gets stuck due to missed exits
     // pub fn remove_slot_owner(&mut self, slot: U128) {
     //     // assert_eq!(
@@ -168,11 +595,26 @@ impl Contract {
     // }
 }
 
+/// Checks a target is a valid account id, with an optional method
+fn assert_valid_target(target: &str) {
+    let mut parts = target.splitn(2, "::");
+    let contract_id = parts.next().unwrap_or("");
+    assert!(
+        env::is_valid_account_id(contract_id.as_bytes()),
+        "Target contract ID invalid"
+    );
+    if let Some(function_id) = parts.next() {
+        assert!(!function_id.is_empty(), "Target function ID missing");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{storage_fee, Settings, TaskArgs};
+    use crate::views::CALLBACK_MAX_DELAY;
     use near_sdk::json_types::ValidAccountId;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
     use near_sdk::{testing_env, MockedBlockchain};
 
     const BLOCK_START_BLOCK: u64 = 52_201_040;
@@ -204,7 +646,10 @@ mod tests {
             .signer_account_id(accounts(3))
             .predecessor_account_id(accounts(3))
             .build());
-        contract.update_settings(None, Some(10), None, None, None, None, None, None, None);
+        contract.update_settings_with(Settings {
+            slot_granularity: Some(10),
+            ..Default::default()
+        });
     }
 
     #[test]
@@ -216,20 +661,48 @@ mod tests {
         assert_eq!(contract.slot_granularity, SLOT_GRANULARITY);
 
         testing_env!(context.is_view(false).build());
-        contract.update_settings(
-            None,
-            Some(10),
-            Some(true),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        );
+        contract.update_settings_with(Settings {
+            slot_granularity: Some(10),
+            paused: Some(true),
+            ..Default::default()
+        });
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.slot_granularity, 10);
-        assert_eq!(contract.paused, true);
+        assert!(contract.paused);
+    }
+
+    #[test]
+    fn test_update_settings_gas_price_bounds() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.update_settings_with(Settings {
+            gas_price_floor: Some(U128::from(GAS_BASE_PRICE * 2)),
+            gas_price_ceiling: Some(U128::from(GAS_BASE_PRICE * 4)),
+            ..Default::default()
+        });
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.get_gas_price_info(),
+            (
+                U128::from(GAS_BASE_PRICE * 2),
+                U128::from(GAS_BASE_PRICE * 2),
+                U128::from(GAS_BASE_PRICE * 4),
+                vec![]
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Gas price floor cannot be above ceiling")]
+    fn test_update_settings_gas_price_bounds_fail() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.update_settings_with(Settings {
+            gas_price_floor: Some(U128::from(GAS_PRICE_CEILING + 1)),
+            ..Default::default()
+        });
     }
 
     #[test]
@@ -241,21 +714,124 @@ mod tests {
         assert_eq!(contract.slot_granularity, SLOT_GRANULARITY);
 
         testing_env!(context.is_view(false).build());
-        contract.update_settings(
-            None,
-            None,
-            Some(true),
-            None,
-            None,
-            None,
-            Some(vec![U64(2), U64(5)]),
-            None,
-            None,
-        );
+        contract.update_settings_with(Settings {
+            paused: Some(true),
+            agent_task_ratio: Some(vec![U64(2), U64(5)]),
+            ..Default::default()
+        });
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.agent_task_ratio[0], 2);
         assert_eq!(contract.agent_task_ratio[1], 5);
-        assert_eq!(contract.paused, true);
+        assert!(contract.paused);
+    }
+
+    /// Creates an hourly "increment" task against "contract_id"
+    fn create_target_task(contract: &mut Contract, contract_id: ValidAccountId) -> Base64VecU8 {
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(contract_id, "increment", "0 0 */1 * * *")
+        })
+    }
+
+    #[test]
+    fn test_denied_targets() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(false).attached_deposit(ONE_NEAR).build());
+        let task_hash = create_target_task(&mut contract, accounts(3));
+        contract.update_denied_targets(
+            Some(vec![
+                accounts(2).to_string(),
+                format!("{}::increment", accounts(3)),
+            ]),
+            None,
+        );
+        assert!(!contract.is_target_allowed(&accounts(2).to_string(), "anything"));
+        assert!(!contract.is_target_allowed(&accounts(3).to_string(), "increment"));
+        assert!(contract.is_target_allowed(&accounts(3).to_string(), "decrement"));
+
+        // Existing tasks calling a denied target exit instead of executing
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        let current_slot = contract.get_slot_id(None);
+        let promise = contract.execute_task(task_hash.0.clone(), task, None, current_slot);
+        assert!(promise.is_none());
+        assert!(contract.tasks.get(&task_hash.0).is_none());
+        assert_eq!(contract.task_balance_total, 0);
+
+        contract.update_denied_targets(None, Some(vec![accounts(2).to_string()]));
+        assert!(contract.is_target_allowed(&accounts(2).to_string(), "anything"));
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.get_targets(),
+            (vec![format!("{}::increment", accounts(3))], vec![], false)
+        );
+    }
+
+    #[test]
+    fn test_denied_targets_triggers() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(false).attached_deposit(ONE_NEAR).build());
+        let task_hash = create_target_task(&mut contract, accounts(3));
+        let trigger_hash = contract.create_trigger(
+            accounts(2),
+            "should_increment".to_string(),
+            task_hash.clone(),
+            None,
+        );
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.update_denied_targets(Some(vec![accounts(2).to_string()]), None);
+
+        // Existing triggers viewing a denied target are removed instead of called
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.proxy_conditional_call(trigger_hash.clone());
+        assert!(contract.triggers.get(&trigger_hash.0).is_none());
+        assert!(contract.tasks.get(&task_hash.0).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Target is not allowed")]
+    fn test_denied_targets_create_task() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.update_denied_targets(Some(vec![accounts(3).to_string()]), None);
+        testing_env!(context.is_view(false).attached_deposit(ONE_NEAR).build());
+        create_target_task(&mut contract, accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Target is not allowed")]
+    fn test_allowed_targets_only() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.update_allowed_targets(Some(vec![accounts(3).to_string()]), None, Some(true));
+        testing_env!(context.is_view(false).attached_deposit(ONE_NEAR).build());
+        create_target_task(&mut contract, accounts(3));
+        create_target_task(&mut contract, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be owner")]
+    fn test_denied_targets_not_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.update_denied_targets(Some(vec![accounts(2).to_string()]), None);
     }
 
     #[test]
@@ -263,30 +839,31 @@ mod tests {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        let base_agent_storage: u128 = 2260000000000000000000;
+        let base_agent_storage: u128 = 2980000000000000000000;
         contract.calc_balances();
 
         testing_env!(context
             .is_view(false)
-            .attached_deposit(ONE_NEAR * 5)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
             .build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(ONE_NEAR)),
-            Some(200),
-            None,
-        );
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
         contract.register_agent(Some(accounts(1)));
         testing_env!(context.is_view(false).build());
 
         // recalc the balances
         let (surplus, rewards) = contract.calc_balances();
         testing_env!(context.is_view(true).build());
-        assert_eq!(contract.available_balance, 5002260000000000000000000);
-        assert_eq!(surplus.0, 91925740000000000000000000);
+        assert_eq!(
+            contract.available_balance(),
+            5002980000000000000000000 + storage_fee(&contract)
+        );
+        // The storage fee is attached and held as task balance, so it cancels out of the surplus
+        assert_eq!(surplus.0, 91925020000000000000000000);
         assert_eq!(rewards.0, base_agent_storage);
     }
 
@@ -302,4 +879,334 @@ mod tests {
         let (_, _, _, surplus) = contract.get_balances();
         assert_eq!(surplus.0, 91928000000000000000000000);
     }
+
+    #[test]
+    fn test_audit_slots() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        let hash1 = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        let hash2 = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "0 0 */1 * * *")
+        });
+        testing_env!(context.is_view(true).build());
+        let (stale, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert_eq!(stale.len(), 0);
+        assert_eq!(unscheduled.len(), 0);
+
+        // Corrupt the slot index: an orphan hash, and a task missing from its slot
+        testing_env!(context.is_view(false).build());
+        let task1 = contract.tasks.get(&hash1.0).unwrap();
+        let mut slot_tasks = contract.slots.get(&task1.slot.0).unwrap();
+        slot_tasks.push(&vec![0, 1, 2, 3]);
+        contract.slots.insert(&task1.slot.0, &slot_tasks);
+        let task2 = contract.tasks.get(&hash2.0).unwrap();
+        contract.slots.remove(&task2.slot.0);
+
+        testing_env!(context.is_view(true).build());
+        let (stale, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert_eq!(
+            stale,
+            vec![(task1.slot, Base64VecU8::from(vec![0, 1, 2, 3]))]
+        );
+        assert_eq!(unscheduled, vec![hash2.clone()]);
+
+        testing_env!(context.is_view(false).build());
+        let (removed, rescheduled) = contract.audit_slots(None, None, None);
+        assert_eq!(removed.0, 1);
+        assert_eq!(rescheduled.0, 1);
+
+        testing_env!(context.is_view(true).build());
+        let (stale, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert_eq!(stale.len(), 0);
+        assert_eq!(unscheduled.len(), 0);
+        assert_eq!(
+            contract.slots.get(&task2.slot.0).unwrap().to_vec(),
+            vec![hash2.0]
+        );
+    }
+
+    #[test]
+    fn test_audit_slots_page_by_key() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        let hash1 = create_target_task(&mut contract, accounts(3));
+        let hash2 = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "decrement", "*/10 * * * * *")
+        });
+        let slot1 = contract.tasks.get(&hash1.0).unwrap().slot.0;
+        let slot2 = contract.tasks.get(&hash2.0).unwrap().slot.0;
+        assert!(slot2 < slot1);
+
+        // Orphan hashes in both slots
+        for slot in [slot1, slot2].iter() {
+            let mut slot_tasks = contract.slots.get(slot).unwrap();
+            slot_tasks.push(&vec![0, 1, 2, 3]);
+            contract.slots.insert(slot, &slot_tasks);
+        }
+
+        // Each page continues after the last slot of the previous one
+        testing_env!(context.is_view(true).build());
+        let (stale, _) = contract.get_slot_inconsistencies(None, None, Some(U64::from(1)));
+        assert_eq!(
+            stale,
+            vec![(U128::from(slot2), Base64VecU8::from(vec![0, 1, 2, 3]))]
+        );
+        let (stale, _) =
+            contract.get_slot_inconsistencies(Some(U128::from(slot2)), None, Some(U64::from(1)));
+        assert_eq!(
+            stale,
+            vec![(U128::from(slot1), Base64VecU8::from(vec![0, 1, 2, 3]))]
+        );
+        let (stale, _) =
+            contract.get_slot_inconsistencies(Some(U128::from(slot1)), None, Some(U64::from(1)));
+        assert!(stale.is_empty());
+    }
+
+    #[test]
+    fn test_audit_slots_in_flight() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        contract.register_agent(Some(accounts(1)));
+        let slot = contract.tasks.get(&task_hash.0).unwrap().slot.0;
+
+        // Executed, its callback has yet to reschedule it
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(slot as u64)
+            .build());
+        contract.proxy_call(None);
+        assert_eq!(contract.slots.len(), 0);
+        let (_, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert!(unscheduled.is_empty());
+        let (_, rescheduled) = contract.audit_slots(None, None, None);
+        assert_eq!(rescheduled.0, 0);
+
+        // A callback that never rescheduled the task gets reported after a while
+        testing_env!(context
+            .is_view(false)
+            .block_timestamp(slot as u64 + CALLBACK_MAX_DELAY)
+            .build());
+        let (_, unscheduled) = contract.get_slot_inconsistencies(None, None, None);
+        assert_eq!(unscheduled, vec![task_hash]);
+        let (_, rescheduled) = contract.audit_slots(None, None, None);
+        assert_eq!(rescheduled.0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be owner")]
+    fn test_audit_slots_fail() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.audit_slots(None, None, None);
+    }
+
+    #[test]
+    fn test_calc_balances_page() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "0 0 */1 * * *")
+        });
+        contract.register_agent(Some(accounts(1)));
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        let tracked = contract.get_available_balances();
+
+        // One item per page: 2 tasks then 1 agent
+        assert_eq!(contract.calc_balances_page(Some(U64::from(1)), None), None);
+        testing_env!(context.is_view(true).build());
+        assert_eq!(
+            contract.get_balances_progress(),
+            Some((U64::from(1), U64::from(2), U64::from(0), U64::from(1)))
+        );
+        testing_env!(context.is_view(false).build());
+        assert_eq!(contract.calc_balances_page(Some(U64::from(1)), None), None);
+        let paged = contract.calc_balances_page(Some(U64::from(1)), None);
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_balances_progress(), None);
+        assert_eq!(contract.get_available_balances(), tracked);
+
+        // Same outcome as a full run
+        testing_env!(context.is_view(false).build());
+        let full = contract.calc_balances();
+        assert_eq!(paged, Some(full));
+        assert_eq!(contract.get_available_balances(), tracked);
+        assert_eq!(
+            contract.available_balance(),
+            10002980000000000000000000 + storage_fee(&contract) * 2
+        );
+    }
+
+    /// Sums the stored task & agent balances, what the tracked totals should be
+    fn stored_balances(contract: &Contract) -> (U128, U128) {
+        let tasks: Balance = contract
+            .tasks
+            .values()
+            .map(|t| t.total_deposit.0 + t.storage_deposit.0)
+            .sum();
+        let agents: Balance = contract
+            .agent_active_queue
+            .iter()
+            .chain(contract.agent_pending_queue.iter())
+            .map(|id| contract.agents.get(&id).unwrap().balance.0)
+            .sum();
+        (U128::from(tasks), U128::from(agents))
+    }
+
+    #[test]
+    fn test_calc_balances_page_corrects_totals() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        let summed_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        let unsummed_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "0 0 */1 * * *")
+        });
+        contract.register_agent(Some(accounts(1)));
+
+        // Tracked totals drifted away from the stored balances
+        contract.task_balance_total += 7;
+        contract.agent_rewards_total -= 5;
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        assert_eq!(contract.calc_balances_page(Some(U64::from(1)), None), None);
+
+        // Refills land between pages, on a summed & a not yet summed task
+        testing_env!(context.is_view(false).attached_deposit(ONE_NEAR).build());
+        contract.refill_balance(summed_hash);
+        contract.refill_balance(unsummed_hash);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        assert!(contract
+            .calc_balances_page(Some(U64::from(10)), None)
+            .is_some());
+
+        // The drift is gone, refills included
+        assert!(get_logs()
+            .iter()
+            .any(|l| l.starts_with("Summed balances differ from the tracked totals")));
+        let stored = stored_balances(&contract);
+        assert_eq!(contract.get_available_balances(), stored);
+        contract.calc_balances();
+        assert_eq!(contract.get_available_balances(), stored);
+    }
+
+    #[test]
+    fn test_calc_balances_page_task_removed() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        let mut hashes = Vec::new();
+        for function_id in ["increment", "decrement", "reset"] {
+            hashes.push(contract.create_task_with(TaskArgs {
+                recurring: Some(true),
+                deposit: Some(U128::from(ONE_NEAR)),
+                gas: Some(200),
+                ..TaskArgs::new(accounts(3), function_id, "0 0 */1 * * *")
+            }));
+        }
+        contract.task_balance_total += 7;
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        assert_eq!(contract.calc_balances_page(Some(U64::from(1)), None), None);
+
+        // Removing the summed task moves the last one behind the cursor, it gets summed right away
+        contract.remove_task(hashes[0].clone());
+        assert_eq!(
+            contract.tasks.keys_as_vector().get(0),
+            Some(hashes[2].0.clone())
+        );
+        assert!(contract
+            .calc_balances_page(Some(U64::from(10)), None)
+            .is_some());
+        assert_eq!(
+            contract.get_available_balances(),
+            stored_balances(&contract)
+        );
+    }
+
+    #[test]
+    fn test_calc_balances_page_restart() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 5 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(ONE_NEAR)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.register_agent(Some(accounts(1)));
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        assert_eq!(contract.calc_balances_page(Some(U64::from(1)), None), None);
+        assert!(contract
+            .calc_balances_page(Some(U64::from(10)), Some(true))
+            .is_some());
+        assert_eq!(contract.balance_reconciliation, None);
+    }
 }
@@ -0,0 +1,346 @@
+use crate::*;
+use near_sdk::ext_contract;
+use near_sdk::serde_json;
+
+pub const GAS_FOR_STAKING_CALL: Gas = 50_000_000_000_000;
+pub const GAS_FOR_STAKING_CALLBACK: Gas = 10_000_000_000_000;
+pub const GAS_FOR_STAKING_VIEW: Gas = 10_000_000_000_000;
+
+/// Methods of a validator staking pool, see https://github.com/near/core-contracts/tree/master/staking-pool
+#[ext_contract(ext_staking_pool)]
+pub trait ExtStakingPool {
+    fn deposit_and_stake(&mut self);
+    fn unstake(&mut self, amount: U128);
+    fn withdraw(&mut self, amount: U128);
+    fn get_account_total_balance(&self, account_id: AccountId) -> U128;
+}
+
+#[ext_contract(ext_staking_self)]
+pub trait ExtStakingSelf {
+    fn callback_stake(&mut self, amount: U128);
+    fn callback_unstake(&mut self, amount: U128);
+    fn callback_withdraw_stake(&mut self, amount: U128);
+    fn callback_staked_balance(&mut self);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Stakes surplus balance into the configured staking pool, only by owner.
+    /// Stakes all of the surplus reported by `get_balances` if no amount is specified.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet stake_surplus '{"amount": "10000000000000000000000000"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn stake_surplus(&mut self, amount: Option<U128>) -> Promise {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        let (_, _, _, surplus) = self.get_balances();
+        let amount = amount.map(|a| a.0).unwrap_or(surplus.0);
+        assert!(amount > 0, "Nothing to stake");
+        assert!(amount <= surplus.0, "Amount is too high");
+
+        self.internal_stake(amount)
+    }
+
+    /// Starts unstaking balance from the staking pool, only by owner.
+    /// Unstaked balance can be withdrawn once the pool releases it (usually 4 epochs).
+    /// The pool checks the amount against the balance it holds, which includes rewards.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet unstake '{"amount": "10000000000000000000000000"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn unstake(&mut self, amount: U128) -> Promise {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        assert!(amount.0 > 0, "Amount must be greater than zero");
+        let staking_pool_id = self
+            .staking_pool_id
+            .clone()
+            .expect("No staking pool configured");
+
+        let promise = ext_staking_pool::unstake(amount, &staking_pool_id, 0, GAS_FOR_STAKING_CALL)
+            .then(ext_staking_self::callback_unstake(
+                amount,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ));
+        self.then_sync_staked_balance(promise, &staking_pool_id)
+    }
+
+    /// Withdraws unstaked balance from the staking pool back to this contract, only by owner.
+    /// The pool checks the amount against the unstaked balance it holds.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet withdraw_stake '{"amount": "10000000000000000000000000"}' --accountId manager_v1.croncat.testnet
+    /// ```
+    pub fn withdraw_stake(&mut self, amount: U128) -> Promise {
+        assert_eq!(
+            self.owner_id,
+            env::predecessor_account_id(),
+            "Must be owner"
+        );
+        assert!(amount.0 > 0, "Amount must be greater than zero");
+        let staking_pool_id = self
+            .staking_pool_id
+            .clone()
+            .expect("No staking pool configured");
+
+        let promise = ext_staking_pool::withdraw(amount, &staking_pool_id, 0, GAS_FOR_STAKING_CALL)
+            .then(ext_staking_self::callback_withdraw_stake(
+                amount,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ));
+        self.then_sync_staked_balance(promise, &staking_pool_id)
+    }
+
+    /// Sends balance to the staking pool, updating staked balance from the pool once done
+    pub(crate) fn internal_stake(&mut self, amount: Balance) -> Promise {
+        let staking_pool_id = self
+            .staking_pool_id
+            .clone()
+            .expect("No staking pool configured");
+        log!("Staking {} with {}", amount, staking_pool_id);
+
+        let promise =
+            ext_staking_pool::deposit_and_stake(&staking_pool_id, amount, GAS_FOR_STAKING_CALL)
+                .then(ext_staking_self::callback_stake(
+                    U128::from(amount),
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_STAKING_CALLBACK,
+                ));
+        self.then_sync_staked_balance(promise, &staking_pool_id)
+    }
+
+    /// Reads the balance held by the staking pool once "promise" completes, whatever its outcome.
+    /// The pool is the source of truth, as rewards and partial outcomes never show up locally.
+    fn then_sync_staked_balance(&self, promise: Promise, staking_pool_id: &AccountId) -> Promise {
+        promise
+            .then(ext_staking_pool::get_account_total_balance(
+                env::current_account_id(),
+                staking_pool_id,
+                0,
+                GAS_FOR_STAKING_VIEW,
+            ))
+            .then(ext_staking_self::callback_staked_balance(
+                &env::current_account_id(),
+                0,
+                GAS_FOR_STAKING_CALLBACK,
+            ))
+    }
+
+    /// Logic executed on the completion of a stake
+    /// NOTE: On failure, the pool refunds the deposit to this contract
+    #[private]
+    pub fn callback_stake(&mut self, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Expected 1 promise result."
+        );
+        match env::promise_result(0) {
+            PromiseResult::NotReady => {
+                unreachable!()
+            }
+            PromiseResult::Successful(_) => {
+                log!("Staked {}", amount.0);
+            }
+            PromiseResult::Failed => {
+                log!("Staking failed, deposit returned");
+            }
+        }
+    }
+
+    /// Logic executed on the completion of an unstake
+    /// NOTE: Unstaked balance is still held by the pool until withdrawn, so it stays in staked balance
+    #[private]
+    pub fn callback_unstake(&mut self, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Expected 1 promise result."
+        );
+        match env::promise_result(0) {
+            PromiseResult::NotReady => {
+                unreachable!()
+            }
+            PromiseResult::Successful(_) => {
+                log!(
+                    "Unstaked {}, available for withdraw after release",
+                    amount.0
+                );
+            }
+            PromiseResult::Failed => {
+                log!("Unstaking failed");
+            }
+        }
+    }
+
+    /// Logic executed on the completion of a withdraw, the balance is back in this contract
+    #[private]
+    pub fn callback_withdraw_stake(&mut self, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Expected 1 promise result."
+        );
+        match env::promise_result(0) {
+            PromiseResult::NotReady => {
+                unreachable!()
+            }
+            PromiseResult::Successful(_) => {
+                log!("Withdrew {} from staking", amount.0);
+            }
+            PromiseResult::Failed => {
+                log!("Withdraw from staking failed");
+            }
+        }
+    }
+
+    /// Logic executed on reading the balance held by the staking pool, staked or pending withdraw
+    /// NOTE: If the pool could not be read, the last known staked balance is kept
+    #[private]
+    pub fn callback_staked_balance(&mut self) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Expected 1 promise result."
+        );
+        match env::promise_result(0) {
+            PromiseResult::NotReady => {
+                unreachable!()
+            }
+            PromiseResult::Successful(result) => {
+                let balance: U128 = serde_json::de::from_slice(&result)
+                    .expect("Could not get staked balance from pool");
+                self.staked_balance = balance.0;
+                log!("Staked balance {}", balance.0);
+            }
+            PromiseResult::Failed => {
+                log!(
+                    "Reading staked balance failed, keeping {}",
+                    self.staked_balance
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{set_promise_result, Settings};
+    use near_sdk::json_types::ValidAccountId;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    const BLOCK_START_BLOCK: u64 = 52_201_040;
+    const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
+
+    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .signer_account_pk(b"ed25519:4ZhGmuKTfQn9ZpHCQVRwEr4JnutL8Uu3kArfxEqksfVM".to_vec())
+            .predecessor_account_id(predecessor_account_id)
+            .block_index(BLOCK_START_BLOCK)
+            .block_timestamp(BLOCK_START_TS);
+        builder
+    }
+
+    #[test]
+    #[should_panic(expected = "No staking pool configured")]
+    fn test_stake_surplus_no_pool() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.stake_surplus(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount is too high")]
+    fn test_stake_surplus_too_high() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.staking_pool_id = Some(accounts(5).to_string());
+        contract.stake_surplus(Some(U128::from(ONE_NEAR * 1000)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must be owner")]
+    fn test_unstake_not_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.unstake(U128::from(ONE_NEAR));
+    }
+
+    #[test]
+    fn test_tick_does_not_stake() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.update_settings_with(Settings {
+            staking_pool_id: Some(accounts(5).to_string()),
+            ..Default::default()
+        });
+        let (_, _, _, surplus) = contract.get_balances();
+        contract.tick();
+        assert_eq!(contract.get_balances().3, surplus);
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Staked balance must be withdrawn before changing staking pool")]
+    fn test_update_settings_staking_pool_staked() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.staking_pool_id = Some(accounts(5).to_string());
+        contract.staked_balance = ONE_NEAR;
+        contract.update_settings_with(Settings {
+            staking_pool_id: Some(accounts(4).to_string()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_callback_staked_balance() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.staked_balance = ONE_NEAR;
+
+        // The pool balance replaces the local one, rewards & over withdraws included
+        set_promise_result(
+            &context,
+            PromiseResult::Successful(b"\"2000000000000000000000000\"".to_vec()),
+        );
+        contract.callback_staked_balance();
+        assert_eq!(contract.staked_balance, ONE_NEAR * 2);
+        set_promise_result(&context, PromiseResult::Successful(b"\"0\"".to_vec()));
+        contract.callback_staked_balance();
+        assert_eq!(contract.staked_balance, 0);
+
+        // An unreadable pool keeps the last known balance
+        contract.staked_balance = ONE_NEAR;
+        set_promise_result(&context, PromiseResult::Failed);
+        contract.callback_staked_balance();
+        assert_eq!(contract.staked_balance, ONE_NEAR);
+    }
+}
@@ -32,7 +32,7 @@ impl StorageManagement for Contract {
         self.register_agent(account_id.clone());
         let account_id = account_id
             .map(|a| a.into())
-            .unwrap_or_else(|| env::predecessor_account_id());
+            .unwrap_or_else(env::predecessor_account_id);
         self.internal_storage_balance_of(&account_id).unwrap()
     }
 
@@ -67,8 +67,18 @@ impl StorageManagement for Contract {
             if balance == 0 || force {
                 self.remove_agent(account_id.clone());
 
+                // Rewards beyond the storage deposit are withdrawn along with it
+                let storage_fee = self.agent_storage_usage as u128 * env::storage_byte_cost();
+                self.agents_stats.total_withdrawals = U128::from(
+                    self.agents_stats
+                        .total_withdrawals
+                        .0
+                        .saturating_add(balance.saturating_sub(storage_fee)),
+                );
+
+                self.sub_agent_rewards(&agent, balance);
+
                 // We add 1 to reimburse for the 1 yoctoⓃ used to call this method
-                self.available_balance = self.available_balance.saturating_sub(balance + 1);
                 Promise::new(account_id).transfer(balance + 1);
                 log!(
                     "Agent has been removed and refunded the storage cost of {}",
@@ -1,8 +1,49 @@
 use crate::*;
+use std::ops::Bound;
 
 pub const MAX_NEAR_GAS: Gas = 300_000_000_000_000;
 pub const GAS_FOR_PROXY_CALL: Gas = 20_000_000_000_000;
 pub const GAS_FOR_PROXY_CALLBACK: Gas = 10_000_000_000_000;
+pub const CATCH_UP_MAX_SCAN: u64 = 24; // most missed occurrences counted & caught up per execution
+pub const GAS_FOR_LOW_BALANCE_HOOK: Gas = 10_000_000_000_000;
+pub const MAX_HOOK_FUNCTION_ID_LEN: usize = 64; // as measured in the task storage
+pub const MAX_TASK_CONTRIBUTORS: usize = 25;
+pub const CONTRIBUTION_STORAGE_USAGE: StorageUsage = 100; // a contribution with a max length account id
+pub const MAX_TASK_DELEGATES: usize = 10;
+pub const DELEGATE_STORAGE_USAGE: StorageUsage = 68; // a delegate entry with a max length account id
+
+/// What to do with cron occurrences missed while no agent executed the task
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CatchUpPolicy {
+    // Default, continue from the next occurrence
+    Skip,
+
+    // Execute one extra time for all missed occurrences
+    RunOnce,
+
+    // Execute once per missed occurrence, up to CATCH_UP_MAX_SCAN
+    RunAllMissed,
+}
+
+/// Contract method called when a task balance runs low, with "task_hash" & "total_deposit" arguments
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceHook {
+    pub contract_id: AccountId,
+    pub function_id: String,
+}
+
+/// Balance added to a task by an account, the owner or a sponsor
+/// Remaining task balance gets refunded in proportion to these amounts
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Contribution {
+    pub account_id: AccountId,
+    pub amount: U128,
+    // Storage paid by a sponsor for this entry, the owner entry is paid with the task storage
+    pub storage_deposit: U128,
+}
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
@@ -34,6 +75,63 @@ pub struct Task {
 
     // NOTE: Only allow static pre-defined bytes
     pub arguments: Base64VecU8,
+
+    /// The slot this task is currently scheduled in
+    /// Allows exact removal, without recomputing the slot from the cadence
+    pub slot: U128,
+
+    /// Extra NEAR paid to the executing agent, per execution.
+    /// Tasks with higher tips get executed first within a slot.
+    pub priority_tip: U128,
+
+    /// Policy for occurrences missed while the task was waiting to be executed
+    pub catch_up: CatchUpPolicy,
+
+    /// Catch up executions left to run, before returning to the cadence
+    pub catch_up_pending: U64,
+
+    /// Total of occurrences missed, for the owner to monitor
+    pub missed_occurrences: U64,
+
+    /// Maximum lateness (in nanos) an execution can have, later executions are skipped.
+    pub max_delay: Option<U64>,
+
+    /// IANA timezone the cadence is evaluated in, like "Europe/Berlin". Defaults to UTC.
+    /// Follows the daylight saving rules of the zone, embedded in the contract.
+    pub timezone: Option<String>,
+
+    /// Total deposit under which the owner gets alerted, to refill before the task exits
+    pub low_balance_threshold: Option<U128>,
+
+    /// Called once the balance drops under the threshold, paid from the task balance
+    pub low_balance_hook: Option<BalanceHook>,
+
+    /// Alert already sent, reset once refilled above the threshold
+    pub low_balance_notified: bool,
+
+    /// Balance added by the owner & sponsors, up to MAX_TASK_CONTRIBUTORS accounts
+    pub contributions: Vec<Contribution>,
+
+    /// Account the owner offered the task to, until it accepts
+    pub pending_owner_id: Option<AccountId>,
+
+    /// Accounts allowed to pause, resume & update the task, but not withdraw or remove it
+    pub delegates: Vec<AccountId>,
+
+    /// Paused tasks are kept out of slots, so they are not executed until resumed
+    pub paused: bool,
+
+    /// Storage paid on creation & by new sponsors, refunded to the owner once the task exits
+    pub storage_deposit: U128,
+
+    /// Block timestamp (NANOS) of the last execution, its callback then reschedules the task
+    pub last_executed: U64,
+
+    /// Gas the owner expects the call to use, the rest of "gas" is refunded to the task unless the call fails
+    pub gas_estimate: Option<Gas>,
+
+    /// Paged `calc_balances_page` run that last summed the task balance
+    pub balances_run: u64,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Serialize, Deserialize, PartialEq)]
@@ -48,15 +146,66 @@ pub struct TaskHumanFriendly {
     pub deposit: U128,
     pub gas: Gas,
     pub arguments: Base64VecU8,
+    pub priority_tip: U128,
+    pub catch_up: CatchUpPolicy,
+    pub missed_occurrences: U64,
+    pub max_delay: Option<U64>,
+    pub timezone: Option<String>,
+    pub low_balance_threshold: Option<U128>,
+    pub low_balance_hook: Option<BalanceHook>,
+    pub contributions: Vec<Contribution>,
+    pub pending_owner_id: Option<AccountId>,
+    pub delegates: Vec<AccountId>,
+    pub paused: bool,
+    pub gas_estimate: Option<Gas>,
     pub hash: Base64VecU8,
 }
 
+impl From<(Vec<u8>, Task)> for TaskHumanFriendly {
+    fn from((hash, task): (Vec<u8>, Task)) -> Self {
+        TaskHumanFriendly {
+            owner_id: task.owner_id,
+            contract_id: task.contract_id,
+            function_id: task.function_id,
+            cadence: task.cadence,
+            recurring: task.recurring,
+            total_deposit: task.total_deposit,
+            deposit: task.deposit,
+            gas: task.gas,
+            arguments: task.arguments,
+            priority_tip: task.priority_tip,
+            catch_up: task.catch_up,
+            missed_occurrences: task.missed_occurrences,
+            max_delay: task.max_delay,
+            timezone: task.timezone,
+            low_balance_threshold: task.low_balance_threshold,
+            low_balance_hook: task.low_balance_hook,
+            contributions: task.contributions,
+            pending_owner_id: task.pending_owner_id,
+            delegates: task.delegates,
+            paused: task.paused,
+            gas_estimate: task.gas_estimate,
+            hash: Base64VecU8::from(hash),
+        }
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     /// Allows any user or contract to pay for future txns based on a specific schedule
     /// contract, function id & other settings. When the task runs out of balance
     /// the task is no longer executed, any additional funds will be returned to task owner.
     ///
+    /// NOTE: The attached deposit also pays for the task storage, see `get_task_storage_fee`.
+    /// It is not used for executions, and gets refunded to the owner once the task exits.
+    ///
+    /// Optional Parameters:
+    /// "priority_tip" - Paid to the agent on top of the agent fee for each execution, gets the task executed earlier in congested slots.
+    /// "catch_up" - "Skip" (default), "RunOnce" or "RunAllMissed", for occurrences missed while agents were behind. Recurring tasks only.
+    /// "max_delay" - Nanoseconds an execution can be late, otherwise it is skipped for a small fee & rescheduled.
+    /// "timezone" - IANA timezone for the cadence hours & days, like "America/New_York". Defaults to UTC.
+    /// "gas_estimate" - Gas the call is expected to use, at most "gas". The agent is paid for the estimate, the gas above it is refunded to the task once the call succeeds, or paid to the agent if it fails.
+    ///
     /// ```bash
     /// near call manager_v1.croncat.testnet create_task '{"contract_id": "counter.in.testnet","function_id": "increment","cadence": "0 0 */1 * * *","recurring": true,"deposit": 0,"gas": 2400000000000}' --accountId YOU.testnet
     /// ```
@@ -70,17 +219,22 @@ impl Contract {
         deposit: Option<U128>,
         gas: Option<Gas>,
         arguments: Option<Base64VecU8>,
+        priority_tip: Option<U128>,
+        catch_up: Option<CatchUpPolicy>,
+        max_delay: Option<U64>,
+        timezone: Option<String>,
+        gas_estimate: Option<Gas>,
     ) -> Base64VecU8 {
         // No adding tasks while contract is paused
-        assert_eq!(self.paused, false, "Create task paused");
+        assert!(!self.paused, "Create task paused");
         // check cadence can be parsed
         assert!(
-            self.validate_cadence(cadence.clone()),
+            self.validate_cadence(cadence.clone(), timezone.clone()),
             "Cadence string invalid"
         );
         // prevent dumb mistakes
-        assert!(contract_id.to_string().len() > 0, "Contract ID missing");
-        assert!(function_id.len() > 0, "Function ID missing");
+        assert!(!contract_id.to_string().is_empty(), "Contract ID missing");
+        assert!(!function_id.is_empty(), "Function ID missing");
         // Tasks will fail if they specify more than available gas
         assert!(
             MAX_NEAR_GAS
@@ -89,6 +243,14 @@ impl Contract {
                     .saturating_add(GAS_FOR_PROXY_CALL.saturating_add(GAS_FOR_PROXY_CALLBACK)),
             "Maximum gas allocation exceeded"
         );
+        assert!(
+            gas_estimate.is_none_or(|estimate| estimate <= gas.unwrap_or(GAS_BASE_FEE)),
+            "Gas estimate exceeds task gas"
+        );
+        assert!(
+            self.is_target_allowed(&contract_id.to_string(), &function_id),
+            "Target is not allowed"
+        );
         // Additional checks
         if contract_id.clone().to_string() == env::current_account_id() {
             // check that the method is NOT the callback of this contract
@@ -108,17 +270,49 @@ impl Contract {
             );
         }
 
-        let item = Task {
+        assert!(
+            self.get_owner_task_count(&env::predecessor_account_id()) < self.max_tasks_per_owner,
+            "Too many tasks for this owner"
+        );
+
+        // Storage is paid out of the attached deposit, the rest is the task balance
+        let arguments = arguments.unwrap_or_else(|| Base64VecU8::from(vec![]));
+        let storage_fee = self.task_storage_fee(&arguments);
+        let task_deposit = env::attached_deposit().saturating_sub(storage_fee);
+
+        // Parse cadence into a future timestamp, then convert to a slot
+        let next_slot = self.get_slot_from_cadence(cadence.clone(), timezone.clone());
+
+        let mut item = Task {
             owner_id: env::predecessor_account_id(),
             contract_id: contract_id.into(),
             function_id,
             cadence,
             recurring: recurring.unwrap_or(false),
-            total_deposit: U128::from(env::attached_deposit()),
+            total_deposit: U128::from(task_deposit),
             deposit: U128::from(deposit.map(|v| v.0).unwrap_or(0u128)),
             gas: gas.unwrap_or(GAS_BASE_FEE),
-            arguments: arguments.unwrap_or_else(|| Base64VecU8::from(vec![])),
+            arguments,
+            slot: U128::from(next_slot),
+            priority_tip: priority_tip.unwrap_or(U128::from(0)),
+            catch_up: catch_up.unwrap_or(CatchUpPolicy::Skip),
+            catch_up_pending: U64::from(0),
+            missed_occurrences: U64::from(0),
+            max_delay,
+            timezone,
+            low_balance_threshold: None,
+            low_balance_hook: None,
+            low_balance_notified: false,
+            contributions: Vec::new(),
+            pending_owner_id: None,
+            delegates: Vec::new(),
+            paused: false,
+            storage_deposit: U128::from(storage_fee),
+            last_executed: U64::from(0),
+            gas_estimate,
+            balances_run: 0,
         };
+        add_contribution(&mut item, env::predecessor_account_id(), task_deposit, 0);
 
         // Check that balance is sufficient for 1 execution minimum
         let call_balance_used = self.task_balance_uses(&item);
@@ -129,8 +323,9 @@ impl Contract {
         };
         assert!(
             min_balance_needed <= item.total_deposit.0,
-            "Not enough task balance to execute job, need at least {}",
-            min_balance_needed
+            "Not enough task balance to execute job, need at least {} plus {} for storage",
+            min_balance_needed,
+            storage_fee
         );
 
         let hash = self.get_hash(
@@ -141,9 +336,6 @@ impl Contract {
             item.arguments.clone(),
         );
 
-        // Parse cadence into a future timestamp, then convert to a slot
-        let next_slot = self.get_slot_from_cadence(item.cadence.clone());
-
         // Add task to catalog
         assert!(
             self.tasks.insert(&hash.0, &item).is_none(),
@@ -151,23 +343,24 @@ impl Contract {
         );
 
         // Get previous task hashes in slot, add as needed
-        let mut slot_slots = self.slots.get(&next_slot).unwrap_or(Vec::new());
-        slot_slots.push(hash.0.clone());
+        let next_slot = self.push_slot_task(next_slot, &hash.0);
+        if next_slot != item.slot.0 {
+            item.slot = U128::from(next_slot);
+            self.tasks.insert(&hash.0, &item);
+        }
         log!("Task next slot: {}", next_slot);
-        self.slots.insert(&next_slot, &slot_slots);
 
         // Keep track of which tasks are owned by whom
-        let mut owner_tasks = self.task_owners.get(&item.owner_id).unwrap_or(Vec::new());
-        owner_tasks.push(hash.0.clone());
+        self.add_owner_task(&item.owner_id, &hash.0);
         log!("Task owner list: {}", item.owner_id);
-        self.task_owners.insert(&item.owner_id, &owner_tasks);
 
-        // Add the attached balance into available_balance
-        self.available_balance = self
-            .available_balance
-            .saturating_add(env::attached_deposit());
+        // Index by target, so contracts can discover the tasks calling them
+        self.add_contract_task(&item.contract_id, &hash.0);
 
-        Base64VecU8::from(hash)
+        // Add the attached balance into the task balances, storage included
+        self.add_task_balance(&item, env::attached_deposit());
+
+        hash
     }
 
     /// Deletes a task in its entirety, returning any remaining balance to task owner.
@@ -190,8 +383,10 @@ impl Contract {
     }
 
     /// Refill a task with more balance to continue its execution
-    /// NOTE: Sending balance here for a task that doesnt exist will result in loss of funds, or you could just use this as an opportunity for donations :D
-    /// NOTE: Currently restricting this to owner only, so owner can make sure the task ends
+    /// Anyone can sponsor a task, the remaining balance is refunded to the owner & sponsors
+    /// in proportion to what they added, once the task exits.
+    /// New sponsors pay the storage of their contribution (CONTRIBUTION_STORAGE_USAGE bytes)
+    /// and add at least as much to the balance. It is refunded to them once the task exits.
     ///
     /// ```bash
     /// near call manager_v1.croncat.testnet refill_balance '{"task_hash": ""}' --accountId YOU.testnet --amount 5
@@ -200,20 +395,351 @@ impl Contract {
     pub fn refill_balance(&mut self, task_hash: Base64VecU8) {
         let hash = task_hash.0;
         let mut task = self.tasks.get(&hash).expect("No task found by hash");
+        let account_id = env::predecessor_account_id();
+        let mut amount = env::attached_deposit();
+        assert!(amount > 0, "Refill needs an attached deposit");
+
+        // Charge the storage of a new contribution, keeping dust from filling the sponsors
+        let mut contribution_fee = 0;
+        if !task
+            .contributions
+            .iter()
+            .any(|c| c.account_id == account_id)
+        {
+            contribution_fee = self.contribution_storage_fee();
+            assert!(
+                amount >= contribution_fee * 2,
+                "New sponsors need to attach at least {}, half of it for storage",
+                contribution_fee * 2
+            );
+            task.storage_deposit = U128::from(task.storage_deposit.0 + contribution_fee);
+            amount -= contribution_fee;
+        }
+
+        // Update task total balance
+        add_contribution(&mut task, account_id, amount, contribution_fee);
+        task.total_deposit = U128::from(task.total_deposit.0.saturating_add(amount));
+        if let Some(threshold) = task.low_balance_threshold {
+            if task.total_deposit.0 >= threshold.0 {
+                task.low_balance_notified = false;
+            }
+        }
+        self.tasks.insert(&hash, &task);
+
+        // Add the attached balance into the task balances, storage included
+        self.add_task_balance(&task, env::attached_deposit());
+    }
+
+    /// Configures the low balance alert of a task, only by the task owner or delegates.
+    /// Once an execution leaves the balance under the threshold, a "task_low_balance" event is logged
+    /// and the hook gets called, for example a treasury method that refills the task.
+    /// Both are cleared if not specified.
+    ///
+    /// Optional Parameters:
+    /// "low_balance_threshold" - Total deposit under which the alert is sent.
+    /// "low_balance_hook" - Contract method to call, its gas is paid from the task balance. Must be an allowed target, other than this contract.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet update_low_balance_alert '{"task_hash": "", "low_balance_threshold": "5000000000000000000000000", "low_balance_hook": {"contract_id": "treasury.YOU.testnet", "function_id": "refill_croncat"}}' --accountId YOU.testnet
+    /// ```
+    pub fn update_low_balance_alert(
+        &mut self,
+        task_hash: Base64VecU8,
+        low_balance_threshold: Option<U128>,
+        low_balance_hook: Option<BalanceHook>,
+    ) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+        assert_task_manager(&task);
+
+        if let Some(hook) = &low_balance_hook {
+            assert!(low_balance_threshold.is_some(), "Hook needs a threshold");
+            assert!(!hook.function_id.is_empty(), "Function ID missing");
+            assert!(
+                hook.function_id.len() <= MAX_HOOK_FUNCTION_ID_LEN,
+                "Function ID too long"
+            );
+            assert!(
+                env::is_valid_account_id(hook.contract_id.as_bytes()),
+                "Contract ID invalid"
+            );
+            // Same targets as the task itself, but never this contract
+            assert!(
+                hook.contract_id != env::current_account_id(),
+                "Hook cannot call this contract"
+            );
+            assert!(
+                self.is_target_allowed(&hook.contract_id, &hook.function_id),
+                "Target is not allowed"
+            );
+        }
+
+        task.low_balance_threshold = low_balance_threshold;
+        task.low_balance_hook = low_balance_hook;
+        task.low_balance_notified = false;
+        self.tasks.insert(&hash, &task);
+    }
+
+    /// Offers the ownership of a task to another account, which has to accept it.
+    /// Cancels a pending offer if no account is specified.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet transfer_task_ownership '{"task_hash": "", "new_owner_id": "NEW_OWNER.testnet"}' --accountId YOU.testnet
+    /// ```
+    pub fn transfer_task_ownership(
+        &mut self,
+        task_hash: Base64VecU8,
+        new_owner_id: Option<ValidAccountId>,
+    ) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+
+        assert_eq!(
+            task.owner_id,
+            env::predecessor_account_id(),
+            "Only owner can transfer their task"
+        );
+
+        task.pending_owner_id = new_owner_id.map(|a| a.into());
+        self.tasks.insert(&hash, &task);
+    }
+
+    /// Accepts the ownership of a task offered with `transfer_task_ownership`.
+    /// The previous owner contribution to the task balance moves along, and delegates are cleared,
+    /// refunding their storage to the previous owner.
+    /// Triggers created by the previous owner stop executing the task, the new owner needs to create its own.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet accept_task_ownership '{"task_hash": ""}' --accountId NEW_OWNER.testnet
+    /// ```
+    pub fn accept_task_ownership(&mut self, task_hash: Base64VecU8) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+        let new_owner_id = env::predecessor_account_id();
+
+        assert_eq!(
+            task.pending_owner_id,
+            Some(new_owner_id.clone()),
+            "Task ownership was not offered to you"
+        );
+
+        // Move the task between owner lists
+        assert!(
+            self.get_owner_task_count(&new_owner_id) < self.max_tasks_per_owner,
+            "Too many tasks for this owner"
+        );
+        self.remove_owner_task(&task.owner_id, &hash);
+        self.add_owner_task(&new_owner_id, &hash);
+
+        // The owner share of the balance belongs to the task ownership
+        if let Some(index) = task
+            .contributions
+            .iter()
+            .position(|c| c.account_id == task.owner_id)
+        {
+            let owner_contribution = task.contributions.remove(index);
+            add_contribution(
+                &mut task,
+                new_owner_id.clone(),
+                owner_contribution.amount.0,
+                0,
+            );
+        }
+
+        // A sponsor taking over now holds the owner entry, paid with the task storage
+        if let Some(contribution) = task
+            .contributions
+            .iter_mut()
+            .find(|c| c.account_id == new_owner_id && c.storage_deposit.0 > 0)
+        {
+            let storage_refund = u128::min(contribution.storage_deposit.0, task.storage_deposit.0);
+            contribution.storage_deposit = U128::from(0);
+            task.storage_deposit = U128::from(task.storage_deposit.0 - storage_refund);
+            self.sub_task_balance(&task, storage_refund);
+            Promise::new(new_owner_id.clone()).transfer(storage_refund);
+        }
+
+        log!(
+            "Task owner changed from {} to {}",
+            task.owner_id,
+            new_owner_id
+        );
+        // Delegates were paid for by the previous owner
+        let delegates_fee = u128::min(
+            self.delegate_storage_fee() * task.delegates.len() as u128,
+            task.storage_deposit.0,
+        );
+        if delegates_fee > 0 {
+            task.storage_deposit = U128::from(task.storage_deposit.0 - delegates_fee);
+            self.sub_task_balance(&task, delegates_fee);
+            Promise::new(task.owner_id.clone()).transfer(delegates_fee);
+        }
+
+        task.owner_id = new_owner_id;
+        task.pending_owner_id = None;
+        task.delegates = Vec::new();
+        self.tasks.insert(&hash, &task);
+    }
+
+    /// Sets the accounts allowed to manage a task, only by the task owner.
+    /// Delegates can pause, resume & update the task, but not withdraw its balance or remove it.
+    /// Each added delegate pays DELEGATE_STORAGE_USAGE bytes of storage, any extra deposit is refunded
+    /// and the storage of removed delegates goes back to the owner.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet update_task_delegates '{"task_hash": "", "delegates": ["OPS.testnet"]}' --accountId YOU.testnet --amount 0.00068
+    /// ```
+    #[payable]
+    pub fn update_task_delegates(
+        &mut self,
+        task_hash: Base64VecU8,
+        delegates: Vec<ValidAccountId>,
+    ) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+
+        assert_eq!(
+            task.owner_id,
+            env::predecessor_account_id(),
+            "Only owner can update task delegates"
+        );
+        assert!(
+            delegates.len() <= MAX_TASK_DELEGATES,
+            "Too many delegates, max is {}",
+            MAX_TASK_DELEGATES
+        );
+
+        // Charge or release the storage of the delegate entries
+        let delegate_fee = self.delegate_storage_fee();
+        let previous_fee = delegate_fee * task.delegates.len() as u128;
+        let required_fee = delegate_fee * delegates.len() as u128;
+        let attached = env::attached_deposit();
+        let refund = if required_fee > previous_fee {
+            let fee = required_fee - previous_fee;
+            assert!(
+                attached >= fee,
+                "Delegates storage payment of {} required",
+                fee
+            );
+            task.storage_deposit = U128::from(task.storage_deposit.0 + fee);
+            self.add_task_balance(&task, fee);
+            attached - fee
+        } else {
+            let fee = u128::min(previous_fee - required_fee, task.storage_deposit.0);
+            task.storage_deposit = U128::from(task.storage_deposit.0 - fee);
+            self.sub_task_balance(&task, fee);
+            attached + fee
+        };
+
+        task.delegates = delegates.into_iter().map(|a| a.into()).collect();
+        self.tasks.insert(&hash, &task);
+
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Pauses a task, taking it out of its slot until resumed. Only by the task owner or delegates.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet pause_task '{"task_hash": ""}' --accountId YOU.testnet
+    /// ```
+    pub fn pause_task(&mut self, task_hash: Base64VecU8) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+        assert_task_manager(&task);
+        assert!(!task.paused, "Task already paused");
+
+        self.remove_slot_task(task.slot.0, &hash);
+        task.paused = true;
+        self.tasks.insert(&hash, &task);
+    }
+
+    /// Resumes a paused task, scheduling it in the next slot of its cadence. Only by the task owner or delegates.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet resume_task '{"task_hash": ""}' --accountId YOU.testnet
+    /// ```
+    pub fn resume_task(&mut self, task_hash: Base64VecU8) {
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
+        assert_task_manager(&task);
+        assert!(task.paused, "Task is not paused");
+
+        let next_slot = self.get_slot_from_cadence(task.cadence.clone(), task.timezone.clone());
+        task.slot = U128::from(self.push_slot_task(next_slot, &hash));
+        task.paused = false;
+        self.tasks.insert(&hash, &task);
+    }
+
+    /// Allows a task owner to withdraw part of the task balance, without removing the task.
+    /// Keeps enough balance for at least 1 more execution, otherwise use `remove_task`.
+    /// Sponsored balance stays in the task, only the owner share can be withdrawn.
+    /// Requires attaching 1 yoctoⓃ ensure it comes from a full-access key.
+    ///
+    /// Optional Parameters:
+    /// "amount" - Amount to withdraw from the task, defaults to all the owner share but 1 execution.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet withdraw_task_deposit '{"task_hash": "r2Jv…T4U4=", "amount": "1000000000000000000000000"}' --accountId YOU.testnet --depositYocto 1
+    /// ```
+    #[payable]
+    pub fn withdraw_task_deposit(
+        &mut self,
+        task_hash: Base64VecU8,
+        amount: Option<U128>,
+    ) -> Promise {
+        assert_one_yocto();
+        let hash = task_hash.0;
+        let mut task = self.tasks.get(&hash).expect("No task found by hash");
 
         assert_eq!(
             task.owner_id,
             env::predecessor_account_id(),
-            "Only owner can refill their task"
+            "Only owner can withdraw their task balance"
         );
 
+        // Sponsored balance stays in the task, owners can only take their share
+        let contributed: u128 = task.contributions.iter().map(|c| c.amount.0).sum();
+        let owner_index = task
+            .contributions
+            .iter()
+            .position(|c| c.account_id == task.owner_id);
+        let owner_share = match owner_index {
+            Some(i) => pro_rata(
+                task.total_deposit.0,
+                task.contributions[i].amount.0,
+                contributed,
+            ),
+            None => 0,
+        };
+        let withdrawable = u128::min(
+            task.total_deposit
+                .0
+                .saturating_sub(self.task_balance_uses(&task)),
+            owner_share,
+        );
+        let amount = amount.map(|a| a.0).unwrap_or(withdrawable);
+        assert!(amount > 0, "Nothing to withdraw");
+        assert!(
+            amount <= withdrawable,
+            "Task balance must cover 1 more execution, can withdraw up to {}",
+            withdrawable
+        );
+
+        // Reduce the owner contribution by the same proportion, keeping sponsor shares unchanged
+        if let Some(i) = owner_index {
+            let burned = pro_rata(amount, contributed, task.total_deposit.0);
+            let owner_contribution = &mut task.contributions[i].amount;
+            *owner_contribution = U128::from(owner_contribution.0.saturating_sub(burned));
+        }
+
         // Update task total balance
-        let amount = env::attached_deposit();
-        task.total_deposit = U128::from(task.total_deposit.0.saturating_add(amount));
+        task.total_deposit = U128::from(task.total_deposit.0 - amount);
         self.tasks.insert(&hash, &task);
+        self.sub_task_balance(&task, amount);
 
-        // Add the attached balance into available_balance
-        self.available_balance = self.available_balance.saturating_add(amount);
+        Promise::new(task.owner_id).transfer(amount)
     }
 
     /// Internal management of finishing a task.
@@ -221,130 +747,502 @@ impl Contract {
     /// returning any remaining balance to task owner.
     pub(crate) fn exit_task(&mut self, task_hash: Vec<u8>) {
         let task = self
-            .tasks
-            .remove(&task_hash)
+            .remove_task_entry(&task_hash)
             .expect("No task found by hash");
 
-        // return any balance
-        if task.total_deposit.0 > 0 {
-            let task_balance_remaining = task.total_deposit.0;
-            self.available_balance = self
-                .available_balance
-                .saturating_sub(task_balance_remaining);
-            Promise::new(task.owner_id.to_string()).transfer(task_balance_remaining);
+        // return any balance & storage, sponsors get their share plus the storage of
+        // their contribution, the owner gets the rest
+        let mut owner_refund = task.total_deposit.0.saturating_add(task.storage_deposit.0);
+        self.sub_task_balance(&task, owner_refund);
+        let contributed: u128 = task.contributions.iter().map(|c| c.amount.0).sum();
+        for sponsor in task
+            .contributions
+            .iter()
+            .filter(|c| c.account_id != task.owner_id)
+        {
+            let refund = u128::min(
+                pro_rata(task.total_deposit.0, sponsor.amount.0, contributed)
+                    .saturating_add(sponsor.storage_deposit.0),
+                owner_refund,
+            );
+            if refund > 0 {
+                owner_refund -= refund;
+                Promise::new(sponsor.account_id.clone()).transfer(refund);
+            }
         }
-
-        // Remove task from schedule
-        // Get task hashes in slot, find index of task hash, remove
-        // TODO: Check that this actually removes task from all slots!
-        let next_slot = self.get_slot_from_cadence(task.cadence.clone());
-        let mut slot_tasks = self.slots.get(&next_slot).unwrap_or(Vec::new());
-        if slot_tasks.len() != 0 {
-            slot_tasks.retain(|h| h != &task_hash);
-            self.slots.insert(&next_slot, &slot_tasks);
+        if owner_refund > 0 {
+            Promise::new(task.owner_id.to_string()).transfer(owner_refund);
         }
 
+        // Remove task from schedule, using the slot it was stored in
+        self.remove_slot_task(task.slot.0, &task_hash);
+
         // Remove task from owners list of tasks
-        // Get task hashes in owner list, find index of task hash, remove
-        let mut owner_tasks = self.task_owners.get(&task.owner_id).unwrap_or(Vec::new());
-        if owner_tasks.len() != 0 {
-            owner_tasks.retain(|h| h != &task_hash);
+        self.remove_owner_task(&task.owner_id, &task_hash);
+
+        // Remove task from the target contract index
+        self.remove_contract_task(&task.contract_id, &task_hash);
+    }
+
+    /// Adds a task hash to the tasks of an owner, each owner gets its own set
+    pub(crate) fn add_owner_task(&mut self, owner_id: &AccountId, task_hash: &Vec<u8>) {
+        let mut owner_tasks = self.task_owners.get(owner_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKeys::OwnerTasks {
+                account_id_hash: env::sha256(owner_id.as_bytes()),
+            })
+        });
+        owner_tasks.insert(task_hash);
+        self.task_owners.insert(owner_id, &owner_tasks);
+    }
+
+    /// Removes a task hash from the tasks of an owner, cleaning up the owner if nothing remains
+    fn remove_owner_task(&mut self, owner_id: &AccountId, task_hash: &Vec<u8>) {
+        if let Some(mut owner_tasks) = self.task_owners.get(owner_id) {
+            owner_tasks.remove(task_hash);
+            if owner_tasks.is_empty() {
+                self.task_owners.remove(owner_id);
+            } else {
+                self.task_owners.insert(owner_id, &owner_tasks);
+            }
+        }
+    }
+
+    /// Adds a task hash to the tasks calling a target contract
+    pub(crate) fn add_contract_task(&mut self, contract_id: &AccountId, task_hash: &Vec<u8>) {
+        let mut contract_tasks = self.task_contracts.get(contract_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKeys::ContractTasks {
+                account_id_hash: env::sha256(contract_id.as_bytes()),
+            })
+        });
+        contract_tasks.insert(task_hash);
+        self.task_contracts.insert(contract_id, &contract_tasks);
+    }
 
-            if owner_tasks.len() > 0 {
-                self.task_owners.insert(&task.owner_id, &owner_tasks);
+    /// Removes a task hash from the tasks calling a target contract, cleaning up once empty
+    fn remove_contract_task(&mut self, contract_id: &AccountId, task_hash: &Vec<u8>) {
+        if let Some(mut contract_tasks) = self.task_contracts.get(contract_id) {
+            contract_tasks.remove(task_hash);
+            if contract_tasks.is_empty() {
+                self.task_contracts.remove(contract_id);
             } else {
-                self.task_owners.remove(&task.owner_id);
+                self.task_contracts.insert(contract_id, &contract_tasks);
             }
         }
     }
 
+    /// Number of tasks owned by an account
+    pub(crate) fn get_owner_task_count(&self, owner_id: &AccountId) -> u64 {
+        self.task_owners
+            .get(owner_id)
+            .map(|owner_tasks| owner_tasks.len())
+            .unwrap_or(0)
+    }
+
     /// Internal management of agent reward
     /// Used in cases where there are empty slots or failed txns
     /// Keep the agent profitable, as this will be a business expense
-    pub(crate) fn send_base_agent_reward(&mut self, _agent: Agent) {
+    /// Counted as an empty slot reward, or as a failed call if "failed_call"
+    pub(crate) fn send_base_agent_reward(
+        &mut self,
+        agent_id: AccountId,
+        _agent: Agent,
+        failed_call: bool,
+    ) {
         let mut agent = _agent;
         // reward agent for diligence
         let agent_base_fee = self.agent_fee;
         agent.balance = U128::from(agent.balance.0.saturating_add(agent_base_fee));
-        agent.total_tasks_executed = U128::from(agent.total_tasks_executed.0.saturating_add(1));
-        self.available_balance = self.available_balance.saturating_sub(agent_base_fee);
+        agent.total_earnings = U128::from(agent.total_earnings.0.saturating_add(agent_base_fee));
+        self.agents_stats.total_earnings = U128::from(
+            self.agents_stats
+                .total_earnings
+                .0
+                .saturating_add(agent_base_fee),
+        );
+        if failed_call {
+            agent.total_failed_calls = U128::from(agent.total_failed_calls.0.saturating_add(1));
+            self.agents_stats.total_failed_calls =
+                U128::from(self.agents_stats.total_failed_calls.0.saturating_add(1));
+        } else {
+            agent.total_empty_slot_rewards =
+                U128::from(agent.total_empty_slot_rewards.0.saturating_add(1));
+            self.agents_stats.total_empty_slot_rewards = U128::from(
+                self.agents_stats
+                    .total_empty_slot_rewards
+                    .0
+                    .saturating_add(1),
+            );
+        }
+        // NOTE: Not coming from any task, this is paid out of the contract surplus
+        self.add_agent_rewards(&agent, agent_base_fee);
 
         // Reset missed slot, if any
         if agent.last_missed_slot != 0 {
             agent.last_missed_slot = 0;
         }
-        self.agents.insert(&env::signer_account_id(), &agent);
+        self.agents.insert(&agent_id, &agent);
     }
 
     /// Internal management of agent reward
     /// Used in cases where there are empty slots or failed txns
     /// Keep the agent profitable, as this will be a business expense
     fn clean_slot(&mut self, slot: &u128) {
-        self.slots.remove(slot);
+        if let Some(mut slot_tasks) = self.slots.remove(slot) {
+            slot_tasks.clear();
+        }
         log!("Slot {} cleaned", slot);
     }
 
-    /// Executes a task based on the current task slot
-    /// Computes whether a task should continue further or not
-    /// Makes a cross-contract call with the task configuration
-    /// Called directly by a registered agent
-    ///
-    /// ```bash
-    /// near call manager_v1.croncat.testnet proxy_call --accountId YOU.testnet
-    /// ```
-    // Questions:
-    // Can the call fail and second promise continue?
-    pub fn proxy_call(&mut self) {
-        // No adding tasks while contract is paused
-        assert_eq!(self.paused, false, "Task execution paused");
-
-        // only registered agent signed, because micropayments will benefit long term
-        let agent_opt = self.agents.get(&env::predecessor_account_id());
-        if agent_opt.is_none() {
-            env::panic(b"Agent not registered");
+    /// Keeps the most recent agent reported gas prices, 1 per agent & within the owner bounds,
+    /// and charges executions the median. A new report replaces the previous one of the agent,
+    /// so repeated calls cannot fill the samples. The price never leaves the owner bounds,
+    /// which default to the base price up to GAS_PRICE_CEILING.
+    pub(crate) fn record_gas_price(&mut self, agent_id: &AccountId, gas_price: Balance) {
+        let sample = gas_price
+            .max(self.gas_price_floor)
+            .min(self.gas_price_ceiling);
+        self.gas_price_samples.retain(|(id, _)| id != agent_id);
+        self.gas_price_samples.push((agent_id.clone(), sample));
+        if self.gas_price_samples.len() > GAS_PRICE_SAMPLES {
+            self.gas_price_samples.remove(0);
         }
-        let mut agent = agent_opt.unwrap();
 
-        // Get current slot based on block or timestamp
-        let current_slot = self.get_slot_id(None);
+        let mut sorted: Vec<Balance> = self.gas_price_samples.iter().map(|(_, s)| *s).collect();
+        sorted.sort_unstable();
+        self.gas_price = sorted[sorted.len() / 2];
+    }
 
-        // get task based on current slot
-        // priority goes to tasks that have fallen behind (using floor key)
-        let (slot_opt, slot_ballpark) = if let Some(k) = self.slots.floor_key(&current_slot) {
-            (self.slots.get(&k), k)
-        } else {
-            (self.slots.get(&current_slot), current_slot)
+    /// Add a task hash into a slot, the task must not be waiting in a slot already
+    /// Slots are ordered by priority tip, as agents pop the last task first.
+    /// Equal tips keep the most recently added task last.
+    /// Full slots spill into their sub-slots: slot + 1, slot + 2, ... (in nanos),
+    /// which run in the same slot. Returns the (sub-)slot the task ended up in.
+    /// NOTE: The task must be stored before being added, to read its tip
+    pub(crate) fn push_slot_task(&mut self, slot: u128, task_hash: &[u8]) -> u128 {
+        let mut slot = slot;
+        let mut slot_tasks = loop {
+            match self.slots.get(&slot) {
+                Some(slot_tasks) if slot_tasks.len() >= self.max_tasks_per_slot => slot += 1,
+                Some(slot_tasks) => break slot_tasks,
+                None => break Vector::new(StorageKeys::SlotTasks { slot }),
+            }
         };
 
-        // let mut slot_data = slot_opt.expect("No tasks found in slot");
-        if slot_opt.is_none() {
-            log!("No tasks found in slot, exiting");
-            self.clean_slot(&slot_ballpark);
-            // reward agent for diligence
-            self.send_base_agent_reward(agent);
-            return;
+        // Binary search the position after all lower or equal tips
+        let tip = self.get_priority_tip(task_hash);
+        let (mut low, mut high) = (0, slot_tasks.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.get_priority_tip(&slot_tasks.get(mid).unwrap()) <= tip {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
         }
-        let mut slot_data = slot_opt.unwrap();
 
-        // Clean up slot if no more data
-        if slot_data.is_empty() {
-            self.clean_slot(&slot_ballpark);
-            // reward agent for diligence
-            self.send_base_agent_reward(agent);
-            return;
+        // Shift higher tips up by one, only these get rewritten
+        slot_tasks.push(&task_hash.to_vec());
+        for i in (low + 1..slot_tasks.len()).rev() {
+            let higher = slot_tasks.get(i - 1).unwrap();
+            slot_tasks.replace(i, &higher);
+        }
+        if low + 1 < slot_tasks.len() {
+            slot_tasks.replace(low, &task_hash.to_vec());
         }
+        self.slots.insert(&slot, &slot_tasks);
+        slot
+    }
 
-        // Check if agent has exceeded their slot task allotment
-        // TODO: An agent can check to execute IF slot is +/-1 and their index is within range???
-        let (can_execute, current_agent_index, _) =
-            self.check_agent_can_execute(env::predecessor_account_id(), slot_data.len() as u64);
+    /// The slot tasks get executed from at "current_slot"
+    /// Priority goes to tasks that have fallen behind (using floor key),
+    /// then sub-slots are drained in the order they were filled, the lowest one first.
+    pub(crate) fn get_slot_ballpark(&self, current_slot: u128) -> u128 {
+        let slot_end = current_slot + u128::from(self.slot_granularity) - 1;
+        match self.slots.floor_key(&slot_end) {
+            Some(slot) => {
+                let slot_start = slot - slot % u128::from(self.slot_granularity);
+                self.slots.ceil_key(&slot_start).unwrap_or(slot)
+            }
+            None => current_slot,
+        }
+    }
 
-        // IF previous agent missed, then store their slot missed. We know this is true IF this slot is using slot_ballpark
-        // NOTE: While this isnt perfect, the eventual outcome is fine.
-        //       If agent gets ticked as "missed" for maximum of 1 slot, then fixes the situation on next round.
-        //       If agent truly misses enough slots, they will skip their chance to reset missed slot count and be dropped.
-        if slot_ballpark < current_slot && self.agent_active_queue.len() > 1 {
-            // wrap around logic for non-overflow index
+    /// Counts the tasks left in the slot of "slot_ballpark", all its sub-slots included
+    pub(crate) fn get_slot_task_count(&self, slot_ballpark: u128) -> u64 {
+        let slot_start = slot_ballpark - slot_ballpark % u128::from(self.slot_granularity);
+        let slot_end = slot_start + u128::from(self.slot_granularity);
+        self.slots
+            .range((Bound::Included(slot_start), Bound::Excluded(slot_end)))
+            .map(|(_, slot_tasks)| slot_tasks.len())
+            .sum()
+    }
+
+    fn get_priority_tip(&self, task_hash: &[u8]) -> Balance {
+        self.tasks
+            .get(&task_hash.to_vec())
+            .map(|task| task.priority_tip.0)
+            .unwrap_or(0)
+    }
+
+    /// Remove a task hash from a slot, cleaning up the slot if nothing remains
+    pub(crate) fn remove_slot_task(&mut self, slot: u128, task_hash: &[u8]) {
+        if let Some(mut slot_tasks) = self.slots.get(&slot) {
+            let index = match slot_tasks.iter().position(|h| h == task_hash) {
+                Some(index) => index as u64,
+                None => return,
+            };
+
+            // Shift the following tasks down by one, keeping the priority order
+            for i in index + 1..slot_tasks.len() {
+                let next = slot_tasks.get(i).unwrap();
+                slot_tasks.replace(i - 1, &next);
+            }
+            slot_tasks.pop();
+            if slot_tasks.is_empty() {
+                self.slots.remove(&slot);
+            } else {
+                self.slots.insert(&slot, &slot_tasks);
+            }
+        }
+    }
+
+    /// Internal execution of a single task, shared by agents, owner & triggers
+    /// Charges the task for the call, rewards the executing agent (if any),
+    /// then either exits the task or schedules the callback for its next slot.
+    ///
+    /// Returns the promise of the execution, or None if the task could not afford it
+    /// and has been exited instead.
+    pub(crate) fn execute_task(
+        &mut self,
+        hash: Vec<u8>,
+        mut task: Task,
+        agent_id: Option<AccountId>,
+        current_slot: u128,
+    ) -> Option<u64> {
+        // Fee breakdown:
+        // - Used Gas: Task Txn Fee Cost
+        // - Agent Fee: Incentivize Execution SLA
+        // - Priority Tip: Optional, set by the task owner
+        //
+        // Task Fee Examples:
+        // Total Fee = Gas Fee + Agent Fee + Priority Tip
+        // Total Balance = Task Deposit + Total Fee
+        //
+        // NOTE: Gas cost includes the cross-contract call & internal logic of this contract.
+        // Direct contract gas fee will be lower than task execution costs, however
+        // we require the task owner to appropriately estimate gas for overpayment.
+        // The callback cannot read how much gas the call used, so with a "gas_estimate"
+        // the gas above it is held in the task until the callback: refunded to the task
+        // when the call succeeds, paid to the agent when it fails (maybe out of gas).
+        let call_fee_used = u128::from(task.gas).saturating_mul(self.gas_price);
+        let mut call_total_fee = call_fee_used
+            .saturating_add(self.agent_fee)
+            .saturating_add(task.priority_tip.0);
+        let mut call_total_balance = task.deposit.0.saturating_add(call_total_fee);
+
+        // Targets denied after the task was created are cut off, refunding the task
+        if !self.is_target_allowed(&task.contract_id, &task.function_id) {
+            log!("Task target is not allowed, exiting");
+            self.exit_task(hash);
+            return None;
+        }
+
+        // safety check and not burn too much gas.
+        if call_total_balance > task.total_deposit.0 {
+            log!("Not enough task balance to execute task, exiting");
+            // Process task exit, if no future task can execute
+            self.exit_task(hash);
+            return None;
+        }
+
+        // Alert once when a recurring task drops under its threshold, the hook gas is reimbursed like the task gas
+        let balance_remaining = task.total_deposit.0 - call_total_balance;
+        let low_balance = task.recurring
+            && !task.low_balance_notified
+            && task
+                .low_balance_threshold
+                .is_some_and(|threshold| balance_remaining < threshold.0);
+        let hook_fee = u128::from(GAS_FOR_LOW_BALANCE_HOOK).saturating_mul(self.gas_price);
+        let call_hook = low_balance
+            && balance_remaining >= hook_fee
+            && task
+                .low_balance_hook
+                .as_ref()
+                .is_some_and(|hook| self.is_target_allowed(&hook.contract_id, &hook.function_id));
+        if low_balance {
+            task.low_balance_notified = true;
+        }
+        // The hook is only paid once, following executions cost the plain call balance
+        let next_call_balance = call_total_balance;
+        if call_hook {
+            call_total_fee = call_total_fee.saturating_add(hook_fee);
+            call_total_balance = call_total_balance.saturating_add(hook_fee);
+        }
+
+        // Gas above the owner estimate is settled in the callback
+        let gas_held = task
+            .gas_estimate
+            .map(|estimate| u128::from(task.gas.saturating_sub(estimate)))
+            .unwrap_or(0)
+            .saturating_mul(self.gas_price);
+        call_total_fee = call_total_fee.saturating_sub(gas_held);
+        call_total_balance = call_total_balance.saturating_sub(gas_held);
+
+        // Update agent storage
+        // Increment agent reward & task count
+        // Reward for agent MUST include the amount of gas used as a reimbursement
+        if let Some(agent_id) = &agent_id {
+            let mut agent = self.agents.get(agent_id).expect("Agent not found");
+            agent.balance = U128::from(agent.balance.0.saturating_add(call_total_fee));
+            agent.total_earnings =
+                U128::from(agent.total_earnings.0.saturating_add(call_total_fee));
+            agent.total_tasks_executed = U128::from(agent.total_tasks_executed.0.saturating_add(1));
+            self.agents_stats.total_earnings = U128::from(
+                self.agents_stats
+                    .total_earnings
+                    .0
+                    .saturating_add(call_total_fee),
+            );
+            self.agents_stats.total_tasks_executed =
+                U128::from(self.agents_stats.total_tasks_executed.0.saturating_add(1));
+
+            // Reset missed slot, if any
+            if agent.last_missed_slot != 0 {
+                agent.last_missed_slot = 0;
+            }
+            self.agents.insert(agent_id, &agent);
+            self.add_agent_rewards(&agent, call_total_fee);
+        }
+
+        // Decrease task balance, Update task storage
+        // NOTE: Without an agent, the fee stays in the contract as surplus
+        task.total_deposit = U128::from(task.total_deposit.0.saturating_sub(call_total_balance));
+        task.last_executed = U64::from(env::block_timestamp());
+        self.sub_task_balance(&task, call_total_balance);
+        self.tasks.insert(&hash, &task);
+
+        if low_balance {
+            self.notify_low_balance(&hash, &task, call_hook);
+        }
+
+        // Call external contract with task variables
+        let promise_first = env::promise_create(
+            task.contract_id.clone(),
+            task.function_id.as_bytes(),
+            task.arguments.0.as_slice(),
+            task.deposit.0,
+            task.gas,
+        );
+
+        // if out of balance or non-recurring, exit the task
+        // With gas held, the callback decides once the held gas is settled
+        if gas_held == 0 && (!task.recurring || next_call_balance > task.total_deposit.0) {
+            // Process task exit, if no future task can execute
+            self.exit_task(hash.clone());
+        }
+
+        // Callback records the outcome & schedules recurring tasks
+        let promise_second = env::promise_then(
+            promise_first,
+            env::current_account_id(),
+            b"callback_for_proxy_call",
+            json!({
+                "task_hash": hash,
+                "current_slot": U128::from(current_slot),
+                "agent_id": agent_id,
+                "gas_held": U128::from(gas_held)
+            })
+            .to_string()
+            .as_bytes(),
+            0,
+            GAS_FOR_CALLBACK,
+        );
+        Some(promise_second)
+    }
+
+    /// Executes a task based on the current task slot
+    /// Computes whether a task should continue further or not
+    /// Makes a cross-contract call with the task configuration
+    /// Called directly by a registered agent
+    ///
+    /// Optional Parameters:
+    /// "gas_price" - The gas price the agent is currently paying, used to track network gas price. Kept once per active agent that executed a task, within the owner bounds.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet proxy_call '{"gas_price": "100000000"}' --accountId YOU.testnet
+    /// ```
+    // Questions:
+    // Can the call fail and second promise continue?
+    pub fn proxy_call(&mut self, gas_price: Option<U128>) {
+        if let Some(promise) = self.internal_proxy_call(gas_price, 1).pop() {
+            env::promise_return(promise);
+        }
+    }
+
+    /// Executes up to "max" tasks of the current slot in one transaction
+    /// Limited to the tasks the agent is entitled to, and to the gas attached.
+    /// Each task is dispatched as an independent promise, with its own callback.
+    ///
+    /// ```bash
+    /// near call manager_v1.croncat.testnet proxy_call_batch '{"max": 5}' --accountId YOU.testnet --gas 300000000000000
+    /// ```
+    pub fn proxy_call_batch(&mut self, max: u8, gas_price: Option<U128>) {
+        assert!(max > 0, "Batch must execute at least 1 task");
+        let promises = self.internal_proxy_call(gas_price, u64::from(max));
+        log!("Executed {} tasks", promises.len());
+    }
+
+    /// Internal agent execution of up to "max_tasks" tasks in the current slot
+    /// Returns the promises of the executed tasks
+    fn internal_proxy_call(&mut self, gas_price: Option<U128>, max_tasks: u64) -> Vec<u64> {
+        // No adding tasks while contract is paused
+        assert!(!self.paused, "Task execution paused");
+
+        // only registered agent signed, because micropayments will benefit long term
+        let agent_id = env::predecessor_account_id();
+        let agent_opt = self.agents.get(&agent_id);
+        if agent_opt.is_none() {
+            env::panic(b"Agent not registered");
+        }
+        let agent = agent_opt.unwrap();
+
+        // Get current slot based on block or timestamp
+        let current_slot = self.get_slot_id(None);
+
+        // get task based on current slot
+        // priority goes to tasks that have fallen behind (using floor key)
+        let slot_ballpark = self.get_slot_ballpark(current_slot);
+        let slot_opt = self.slots.get(&slot_ballpark);
+
+        // let mut slot_data = slot_opt.expect("No tasks found in slot");
+        if slot_opt.is_none() {
+            log!("No tasks found in slot, exiting");
+            self.clean_slot(&slot_ballpark);
+            // reward agent for diligence
+            self.send_base_agent_reward(agent_id, agent, false);
+            return Vec::new();
+        }
+        let mut slot_data = slot_opt.unwrap();
+
+        // Clean up slot if no more data
+        if slot_data.is_empty() {
+            self.clean_slot(&slot_ballpark);
+            // reward agent for diligence
+            self.send_base_agent_reward(agent_id, agent, false);
+            return Vec::new();
+        }
+
+        // Check if agent has exceeded their slot task allotment
+        // TODO: An agent can check to execute IF slot is +/-1 and their index is within range???
+        let (can_execute, current_agent_index, agent_tasks) =
+            self.check_agent_can_execute(agent_id.clone(), self.get_slot_task_count(slot_ballpark));
+
+        // IF previous agent missed, then store their slot missed. We know this is true IF this slot is using slot_ballpark
+        // NOTE: While this isnt perfect, the eventual outcome is fine.
+        //       If agent gets ticked as "missed" for maximum of 1 slot, then fixes the situation on next round.
+        //       If agent truly misses enough slots, they will skip their chance to reset missed slot count and be dropped.
+        if slot_ballpark < current_slot && self.agent_active_queue.len() > 1 {
+            // wrap around logic for non-overflow index
             // if only 1 agent, dont do anything
             let missed_agent_index = if current_agent_index == 0 {
                 self.agent_active_queue.len()
@@ -371,7 +1269,7 @@ impl Contract {
             assert!(can_execute, "Agent has exceeded execution for this slot");
 
             // Rotate agent index
-            if self.agent_active_index as u64 == self.agent_active_queue.len().saturating_sub(1) {
+            if self.agent_active_index == self.agent_active_queue.len().saturating_sub(1) {
                 self.agent_active_index = 0;
             } else if self.agent_active_queue.len() > 1 {
                 // Only change the index IF there are more than 1 agents ;)
@@ -379,150 +1277,266 @@ impl Contract {
             }
         }
 
-        // Get a single task hash, then retrieve task details
-        let some_hash = slot_data.pop();
-
-        // After popping, ensure state is rewritten back
-        if slot_data.is_empty() {
-            // Clean up slot if no more data
-            self.clean_slot(&slot_ballpark);
+        // Agents take the tasks they are entitled to in the current slot,
+        // while tasks that have fallen behind can be taken by anyone
+        let max_tasks = if slot_ballpark < current_slot {
+            max_tasks
         } else {
-            self.slots.insert(&slot_ballpark, &slot_data);
+            u64::min(max_tasks, agent_tasks)
+        };
+        let mut promises: Vec<u64> = Vec::new();
+        let mut tasks_handled: u64 = 0;
+
+        while tasks_handled < max_tasks {
+            // Stop once the gas left cannot cover the next task, leaving it in its slot
+            if tasks_handled > 0 {
+                let next_gas = slot_data
+                    .get(slot_data.len().saturating_sub(1))
+                    .and_then(|h| self.tasks.get(&h))
+                    .map(|t| self.get_task_execution_gas(&t))
+                    .unwrap_or(GAS_FOR_CALLBACK);
+                let gas_left = env::prepaid_gas().saturating_sub(env::used_gas());
+                if gas_left < next_gas + GAS_FOR_PROXY_CALL {
+                    log!("Not enough gas for more tasks, exiting");
+                    break;
+                }
+            }
+            tasks_handled += 1;
+
+            // Get a single task hash, then retrieve task details
+            let hash = match slot_data.pop() {
+                Some(hash) => hash,
+                None => break,
+            };
+
+            // After popping, ensure state is rewritten back
+            if slot_data.is_empty() {
+                // Clean up slot if no more data
+                self.clean_slot(&slot_ballpark);
+            } else {
+                self.slots.insert(&slot_ballpark, &slot_data);
+            }
+
+            // if no task, reward agent and move on.
+            // the hash is removed from the slot, as it never gets computed for next slot.
+            let task = match self.tasks.get(&hash) {
+                Some(task) => task,
+                None => {
+                    log!("No task found by hash");
+                    // reward agent for diligence
+                    let agent = self.agents.get(&agent_id).expect("Agent not found");
+                    self.send_base_agent_reward(agent_id.clone(), agent, false);
+                    continue;
+                }
+            };
+
+            // Skip tasks that would be executed too late to be useful
+            if let Some(max_delay) = task.max_delay {
+                if slot_ballpark < current_slot.saturating_sub(u128::from(max_delay.0)) {
+                    log!("Task is past its max delay, skipping");
+                    self.skip_task(hash, task, agent_id.clone());
+                    continue;
+                }
+            }
+
+            if let Some(promise) =
+                self.execute_task(hash, task, Some(agent_id.clone()), current_slot)
+            {
+                promises.push(promise);
+            }
         }
 
-        if some_hash.is_none() {
-            log!("No tasks available, exiting");
-            // reward agent for diligence
-            self.send_base_agent_reward(agent);
-            return;
+        // Contracts cannot read the network gas price, so agents report what they pay.
+        // Only active agents that executed a task get a say in the price.
+        if let Some(gas_price) = gas_price {
+            if !promises.is_empty() && agent.status == agent::AgentStatus::Active {
+                self.record_gas_price(&agent_id, gas_price.0);
+            }
         }
-        let hash = some_hash.unwrap();
-        let some_task = self.tasks.get(&hash);
 
-        // if no task, exit and reward agent.
-        if some_task.is_none() {
-            log!("No task found by hash, exiting");
-            // reward agent for diligence
-            self.send_base_agent_reward(agent);
+        promises
+    }
 
-            // remove the hash from the slot, by simply returning before the hash gets computed for next slot.
+    /// Internal skipping of a late task, instead of executing it.
+    /// Charges the task the agent fee as a skip fee, paid to the agent,
+    /// then reschedules the task to its next occurrence.
+    fn skip_task(&mut self, hash: Vec<u8>, mut task: Task, agent_id: AccountId) {
+        let skip_fee = self.agent_fee;
+        if skip_fee > task.total_deposit.0 {
+            log!("Not enough task balance to skip task, exiting");
+            self.exit_task(hash);
             return;
         }
 
-        let mut task = some_task.unwrap();
+        // Move the skip fee from the task to the agent
+        let mut agent = self.agents.get(&agent_id).expect("Agent not found");
+        agent.balance = U128::from(agent.balance.0.saturating_add(skip_fee));
+        agent.total_earnings = U128::from(agent.total_earnings.0.saturating_add(skip_fee));
+        self.agents_stats.total_earnings =
+            U128::from(self.agents_stats.total_earnings.0.saturating_add(skip_fee));
+        agent.last_missed_slot = 0;
+        self.agents.insert(&agent_id, &agent);
+        self.add_agent_rewards(&agent, skip_fee);
+        task.total_deposit = U128::from(task.total_deposit.0 - skip_fee);
+        self.sub_task_balance(&task, skip_fee);
+        task.missed_occurrences = U64::from(task.missed_occurrences.0 + 1);
+
+        if !task.recurring {
+            self.tasks.insert(&hash, &task);
+            self.exit_task(hash);
+            return;
+        }
 
-        // Fee breakdown:
-        // - Used Gas: Task Txn Fee Cost
-        // - Agent Fee: Incentivize Execution SLA
-        //
-        // Task Fee Examples:
-        // Total Fee = Gas Fee + Agent Fee
-        // Total Balance = Task Deposit + Total Fee
-        //
-        // NOTE: Gas cost includes the cross-contract call & internal logic of this contract.
-        // Direct contract gas fee will be lower than task execution costs, however
-        // we require the task owner to appropriately estimate gas for overpayment.
-        // The gas overpayment will also accrue to the agent since there is no way to read
-        // how much gas was actually used on callback.
-        let call_fee_used = u128::from(task.gas).saturating_mul(self.gas_price);
-        let call_total_fee = call_fee_used.saturating_add(self.agent_fee);
-        let call_total_balance = task.deposit.0.saturating_add(call_total_fee);
+        // Reschedule to the next occurrence, the task was already taken out of its slot
+        let next_slot = self.get_slot_from_cadence(task.cadence.clone(), task.timezone.clone());
+        task.slot = U128::from(self.push_slot_task(next_slot, &hash));
+        self.tasks.insert(&hash, &task);
+    }
 
-        // safety check and not burn too much gas.
-        if call_total_balance > task.total_deposit.0 {
-            log!("Not enough task balance to execute task, exiting");
-            // Process task exit, if no future task can execute
-            return self.exit_task(hash);
+    /// Logic executed on the completion of a proxy call
+    /// Records failed calls, settles the gas held above the task estimate,
+    /// then reschedules the task unless it exited
+    #[private]
+    pub fn callback_for_proxy_call(
+        &mut self,
+        task_hash: Vec<u8>,
+        current_slot: U128,
+        agent_id: Option<AccountId>,
+        gas_held: Option<U128>,
+    ) {
+        let call_failed = env::promise_results_count() == 1
+            && matches!(env::promise_result(0), PromiseResult::Failed);
+
+        // Count failed task calls for the executing agent, the execution was still paid
+        if let Some(agent_id) = &agent_id {
+            if call_failed {
+                if let Some(mut agent) = self.agents.get(agent_id) {
+                    agent.total_failed_calls =
+                        U128::from(agent.total_failed_calls.0.saturating_add(1));
+                    self.agents.insert(agent_id, &agent);
+                    self.agents_stats.total_failed_calls =
+                        U128::from(self.agents_stats.total_failed_calls.0.saturating_add(1));
+                }
+            }
         }
 
-        // Update agent storage
-        // Increment agent reward & task count
-        // Reward for agent MUST include the amount of gas used as a reimbursement
-        agent.balance = U128::from(agent.balance.0.saturating_add(call_total_fee));
-        agent.total_tasks_executed = U128::from(agent.total_tasks_executed.0.saturating_add(1));
-        self.available_balance = self.available_balance.saturating_sub(call_total_fee);
+        // Exited tasks only needed their outcome recorded, their held gas was refunded on exit
+        let mut task = match self.tasks.get(&task_hash) {
+            Some(task) => task,
+            None => return,
+        };
 
-        // Reset missed slot, if any
-        if agent.last_missed_slot != 0 {
-            agent.last_missed_slot = 0;
-        }
-        self.agents.insert(&env::signer_account_id(), &agent);
+        let gas_held = gas_held.map(|g| g.0).unwrap_or(0);
+        if gas_held > 0 {
+            // A failed call may have used all of its gas, reimburse the agent with the held gas
+            // Otherwise it stays in the task balance as a refund
+            if let Some(agent_id) = agent_id.filter(|_| call_failed) {
+                if let Some(mut agent) = self.agents.get(&agent_id) {
+                    let reimbursed = u128::min(gas_held, task.total_deposit.0);
+                    agent.balance = U128::from(agent.balance.0.saturating_add(reimbursed));
+                    agent.total_earnings =
+                        U128::from(agent.total_earnings.0.saturating_add(reimbursed));
+                    self.agents_stats.total_earnings = U128::from(
+                        self.agents_stats
+                            .total_earnings
+                            .0
+                            .saturating_add(reimbursed),
+                    );
+                    self.agents.insert(&agent_id, &agent);
+                    self.add_agent_rewards(&agent, reimbursed);
+                    task.total_deposit = U128::from(task.total_deposit.0 - reimbursed);
+                    self.sub_task_balance(&task, reimbursed);
+                }
+            }
+            self.tasks.insert(&task_hash, &task);
 
-        // Decrease task balance, Update task storage
-        task.total_deposit = U128::from(task.total_deposit.0.saturating_sub(call_total_balance));
-        self.tasks.insert(&hash, &task);
+            // if out of balance or non-recurring, exit the task
+            if !task.recurring || self.task_balance_uses(&task) > task.total_deposit.0 {
+                self.exit_task(task_hash);
+                return;
+            }
+        }
 
-        // Call external contract with task variables
-        let promise_first = env::promise_create(
-            task.contract_id.clone(),
-            &task.function_id.as_bytes(),
-            task.arguments.0.as_slice(),
-            task.deposit.0,
-            task.gas,
-        );
+        // Paused while executing, keep it out of slots until resumed
+        if task.paused {
+            self.remove_slot_task(task.slot.0, &task_hash);
+            return;
+        }
 
-        // if out of balance or non-recurring, exit callback
-        if !task.recurring || call_total_balance > task.total_deposit.0 {
-            // Process task exit, if no future task can execute
-            self.exit_task(hash);
-            env::promise_return(promise_first);
+        // Catch up on missed occurrences first, if the task policy asks for it
+        let next_slot = if let Some(catch_up_slot) = self.get_catch_up_slot(&mut task) {
+            log!("Scheduling Catch Up Task {:?}", &catch_up_slot);
+            catch_up_slot
         } else {
-            // if recurring, callback for scheduling
-            let promise_second = env::promise_then(
-                promise_first,
-                env::current_account_id(),
-                b"callback_for_proxy_call",
-                json!({
-                    "task_hash": hash,
-                    "current_slot": U128::from(current_slot)
-                })
-                .to_string()
-                .as_bytes(),
-                0,
-                GAS_FOR_CALLBACK,
+            // double check this can't get scheduled in current slot again
+            let next_slot = self.get_slot_from_cadence(task.cadence.clone(), task.timezone.clone());
+            log!("Scheduling Next Task {:?}", &next_slot);
+            assert!(
+                current_slot.0 < next_slot,
+                "Cannot schedule task in the past"
             );
-            env::promise_return(promise_second);
-        }
+            next_slot
+        };
+
+        // Triggers & audits can leave the task waiting in a slot, make sure it only lives in one
+        self.remove_slot_task(task.slot.0, &task_hash);
+
+        // Get previous task hashes in slot, add as needed
+        task.slot = U128::from(self.push_slot_task(next_slot, &task_hash));
+        self.tasks.insert(&task_hash, &task);
     }
 
-    /// Logic executed on the completion of a proxy call
-    /// Reschedule next task
-    #[private]
-    pub fn callback_for_proxy_call(&mut self, task_hash: Vec<u8>, current_slot: U128) {
-        let task = self
-            .tasks
-            .get(&task_hash.clone())
-            .expect("No task found by hash");
+    /// Counts the occurrences missed since the slot the task was executed for,
+    /// returning the current slot if a catch up execution should happen there.
+    fn get_catch_up_slot(&self, task: &mut Task) -> Option<u128> {
+        let current_slot = self.get_slot_id(None);
 
-        // double check this can't get scheduled in current slot again
-        let next_slot = self.get_slot_from_cadence(task.cadence.clone());
-        log!("Scheduling Next Task {:?}", &next_slot);
-        assert!(
-            &current_slot.0 < &next_slot,
-            "Cannot schedule task in the past"
-        );
+        if task.catch_up_pending.0 > 0 {
+            // This was a catch up execution
+            task.catch_up_pending = U64::from(task.catch_up_pending.0 - 1);
+        } else {
+            // Walk the slots the task would have been scheduled in, had it run on time
+            let schedule = Schedule::from_str(&task.cadence).unwrap();
+            let mut slot = task.slot.0;
+            let mut missed: u64 = 0;
+            while missed < CATCH_UP_MAX_SCAN {
+                slot = self.get_slot_from_schedule(&schedule, &task.timezone, slot as u64);
+                if slot > current_slot {
+                    break;
+                }
+                missed += 1;
+            }
+            if missed == 0 {
+                return None;
+            }
+            log!("Task missed {} occurrences", missed);
+            task.missed_occurrences = U64::from(task.missed_occurrences.0 + missed);
+            task.catch_up_pending = U64::from(match task.catch_up {
+                CatchUpPolicy::Skip => 0,
+                CatchUpPolicy::RunOnce => 1,
+                CatchUpPolicy::RunAllMissed => missed,
+            });
+        }
 
-        // Get previous task hashes in slot, add as needed
-        let mut slot_tasks = self.slots.get(&next_slot).unwrap_or_default();
-        slot_tasks.push(task_hash.clone());
-        self.slots.insert(&next_slot, &slot_tasks);
+        if task.catch_up_pending.0 > 0 {
+            Some(current_slot)
+        } else {
+            None
+        }
     }
 
     /// Executes a task based on the current task slot
     #[private]
     pub fn proxy_call_owner(&mut self) {
         // No adding tasks while contract is paused
-        assert_eq!(self.paused, false, "Task execution paused");
+        assert!(!self.paused, "Task execution paused");
         // Get current slot based on block or timestamp
         let current_slot = self.get_slot_id(None);
 
         // get task based on current slot
         // priority goes to tasks that have fallen behind (using floor key)
-        let (slot_opt, slot_ballpark) = if let Some(k) = self.slots.floor_key(&current_slot) {
-            (self.slots.get(&k), k)
-        } else {
-            (self.slots.get(&current_slot), current_slot)
-        };
+        let slot_ballpark = self.get_slot_ballpark(current_slot);
+        let slot_opt = self.slots.get(&slot_ballpark);
         log!(
             "slot_ballpark {:?} current_slot {:?}",
             &slot_ballpark,
@@ -550,616 +1564,2443 @@ impl Contract {
             self.slots.insert(&slot_ballpark, &slot_data);
         }
 
-        let mut task = self.tasks.get(&hash).expect("No task found by hash");
-
-        // Fee breakdown:
-        // - Used Gas: Task Txn Fee Cost
-        // - Agent Fee: Incentivize Execution SLA
-        //
-        // Task Fee Examples:
-        // Total Fee = Gas Fee + Agent Fee
-        // Total Balance = Task Deposit + Total Fee
-        //
-        // NOTE: Gas cost includes the cross-contract call & internal logic of this contract.
-        // Direct contract gas fee will be lower than task execution costs, however
-        // we require the task owner to appropriately estimate gas for overpayment.
-        // The gas overpayment will also accrue to the agent since there is no way to read
-        // how much gas was actually used on callback.
-        let call_fee_used = u128::from(task.gas) * self.gas_price;
-        let call_total_fee = call_fee_used + self.agent_fee;
-        let call_total_balance = task.deposit.0 + call_total_fee;
+        let task = self.tasks.get(&hash).expect("No task found by hash");
 
-        // safety check and not burn too much gas.
-        if call_total_balance > task.total_deposit.0 {
-            log!("Not enough task balance to execute task, exiting");
-            // Process task exit, if no future task can execute
-            return self.exit_task(hash);
+        if let Some(promise) = self.execute_task(hash, task, None, current_slot) {
+            env::promise_return(promise);
         }
+    }
 
-        self.available_balance = self.available_balance - call_total_fee;
-
-        // Decrease task balance, Update task storage
-        task.total_deposit = U128::from(task.total_deposit.0 - call_total_balance);
-        self.tasks.insert(&hash, &task);
+    /// Logs the low balance event of a task, and calls its hook if paid for
+    fn notify_low_balance(&self, hash: &[u8], task: &Task, call_hook: bool) {
+        let task_hash = Base64VecU8::from(hash.to_vec());
+        log!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": "croncat",
+                "version": "1.0.0",
+                "event": "task_low_balance",
+                "data": [{
+                    "task_hash": task_hash,
+                    "owner_id": task.owner_id,
+                    "total_deposit": task.total_deposit,
+                }]
+            })
+        );
 
-        // Call external contract with task variables
-        let promise_first = env::promise_create(
-            task.contract_id.clone(),
-            &task.function_id.as_bytes(),
-            task.arguments.0.as_slice(),
-            task.deposit.0,
-            task.gas,
+        if let (true, Some(hook)) = (call_hook, &task.low_balance_hook) {
+            // Targets denied after the hook was set are cut off
+            if !self.is_target_allowed(&hook.contract_id, &hook.function_id) {
+                log!("Low balance hook target is not allowed, skipping");
+                return;
+            }
+            env::promise_create(
+                hook.contract_id.clone(),
+                hook.function_id.as_bytes(),
+                json!({
+                    "task_hash": task_hash,
+                    "total_deposit": task.total_deposit,
+                })
+                .to_string()
+                .as_bytes(),
+                0,
+                GAS_FOR_LOW_BALANCE_HOOK,
+            );
+        }
+    }
+
+    /// Gas needed to execute a task: its own gas, the callback & the low balance hook if armed
+    pub(crate) fn get_task_execution_gas(&self, task: &Task) -> Gas {
+        let hook_armed = task.recurring
+            && !task.low_balance_notified
+            && task.low_balance_threshold.is_some()
+            && task.low_balance_hook.is_some();
+        let hook_gas = if hook_armed {
+            GAS_FOR_LOW_BALANCE_HOOK
+        } else {
+            0
+        };
+        task.gas
+            .saturating_add(GAS_FOR_CALLBACK)
+            .saturating_add(hook_gas)
+    }
+
+    /// Returns the base amount required to execute 1 task
+    /// NOTE: this is not the final used amount, just the user-specified amount total needed
+    pub fn task_balance_uses(&self, task: &Task) -> u128 {
+        self.get_execution_cost(task.deposit.0, task.gas, task.priority_tip.0)
+    }
+
+    /// Balance used by 1 execution, see `task_balance_uses`
+    pub(crate) fn get_execution_cost(&self, deposit: u128, gas: Gas, priority_tip: u128) -> u128 {
+        deposit + (u128::from(gas) * self.gas_price) + self.agent_fee + priority_tip
+    }
+
+    /// Storage paid when creating a task, for the measured task entries & its arguments
+    pub(crate) fn task_storage_fee(&self, arguments: &Base64VecU8) -> Balance {
+        Balance::from(self.task_storage_usage + arguments.0.len() as u64) * env::storage_byte_cost()
+    }
+
+    /// Storage paid by a new sponsor, for its entry in the task contributions
+    pub(crate) fn contribution_storage_fee(&self) -> Balance {
+        Balance::from(CONTRIBUTION_STORAGE_USAGE) * env::storage_byte_cost()
+    }
+
+    /// Storage paid by the task owner, for each entry in the task delegates
+    pub(crate) fn delegate_storage_fee(&self) -> Balance {
+        Balance::from(DELEGATE_STORAGE_USAGE) * env::storage_byte_cost()
+    }
+}
+
+/// Checks the caller is the owner or a delegate of the task
+fn assert_task_manager(task: &Task) {
+    let account_id = env::predecessor_account_id();
+    assert!(
+        task.owner_id == account_id || task.delegates.contains(&account_id),
+        "Only owner or delegates can manage this task"
+    );
+}
+
+/// Adds balance to the contribution of an account, which is new or existing
+/// New sponsors also record the storage they paid for their entry
+fn add_contribution(
+    task: &mut Task,
+    account_id: AccountId,
+    amount: Balance,
+    storage_deposit: Balance,
+) {
+    if amount == 0 {
+        return;
+    }
+    if let Some(contribution) = task
+        .contributions
+        .iter_mut()
+        .find(|c| c.account_id == account_id)
+    {
+        contribution.amount = U128::from(contribution.amount.0.saturating_add(amount));
+    } else {
+        assert!(
+            task.contributions.len() < MAX_TASK_CONTRIBUTORS,
+            "Too many sponsors for this task"
+        );
+        task.contributions.push(Contribution {
+            account_id,
+            amount: U128::from(amount),
+            storage_deposit: U128::from(storage_deposit),
+        });
+    }
+}
+
+/// Computes "amount * part / total" rounded down, without overflowing on NEAR sized balances
+pub(crate) fn pro_rata(amount: u128, part: u128, total: u128) -> u128 {
+    if total == 0 {
+        return 0;
+    }
+    // amount * part / total = (amount / total) * part + (amount % total) * part / total
+    let quotient = (amount / total).saturating_mul(part);
+    let remainder = amount % total;
+
+    // Long multiplication of "remainder * part", keeping it reduced by "total" on every bit
+    let mut result: u128 = 0;
+    let mut carry: u128 = 0;
+    for bit in (0..128).rev() {
+        result = result.saturating_mul(2);
+        carry *= 2;
+        if carry >= total {
+            carry -= total;
+            result += 1;
+        }
+        if (part >> bit) & 1 == 1 {
+            carry += remainder;
+            if carry >= total {
+                carry -= total;
+                result += 1;
+            }
+        }
+    }
+
+    quotient.saturating_add(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_transfers, set_promise_result, storage_fee, Settings, TaskArgs};
+    use near_sdk::json_types::ValidAccountId;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    use chrono::prelude::DateTime;
+    use chrono::Utc;
+    use cron_schedule::Schedule;
+    use std::str::FromStr;
+
+    const BLOCK_START_BLOCK: u64 = 52_201_040;
+    const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
+    const AGENT_STORAGE_FEE: u128 = 2980000000000000000000;
+
+    pub fn get_sample_task() -> Task {
+        Task {
+            owner_id: String::from("bob"),
+            contract_id: String::from("danny"),
+            function_id: String::from("increment"),
+            cadence: String::from("0 0 */1 * * *"),
+            recurring: false,
+            total_deposit: U128::from(1000000000020000000100),
+            deposit: U128::from(100),
+            gas: 200,
+            arguments: Base64VecU8::from(vec![]),
+            slot: U128::from(0),
+            priority_tip: U128::from(0),
+            catch_up: CatchUpPolicy::Skip,
+            catch_up_pending: U64::from(0),
+            missed_occurrences: U64::from(0),
+            max_delay: None,
+            timezone: None,
+            low_balance_threshold: None,
+            low_balance_hook: None,
+            low_balance_notified: false,
+            contributions: Vec::new(),
+            pending_owner_id: None,
+            delegates: Vec::new(),
+            paused: false,
+            storage_deposit: U128::from(0),
+            last_executed: U64::from(0),
+            gas_estimate: None,
+            balances_run: 0,
+        }
+    }
+    pub fn get_sample_task_hr() -> TaskHumanFriendly {
+        TaskHumanFriendly {
+            owner_id: String::from("bob"),
+            contract_id: String::from("danny"),
+            function_id: String::from("increment"),
+            cadence: String::from("0 0 */1 * * *"),
+            recurring: false,
+            total_deposit: U128::from(1000000000020000000100),
+            deposit: U128::from(100),
+            gas: 200,
+            arguments: Base64VecU8::from(vec![]),
+            priority_tip: U128::from(0),
+            catch_up: CatchUpPolicy::Skip,
+            missed_occurrences: U64::from(0),
+            max_delay: None,
+            timezone: None,
+            low_balance_threshold: None,
+            low_balance_hook: None,
+            contributions: vec![Contribution {
+                account_id: String::from("bob"),
+                amount: U128::from(1000000000020000000100),
+                storage_deposit: U128::from(0),
+            }],
+            pending_owner_id: None,
+            delegates: Vec::new(),
+            paused: false,
+            gas_estimate: None,
+            hash: Base64VecU8(vec![
+                25, 109, 16, 117, 147, 91, 137, 42, 231, 234, 13, 62, 155, 180, 27, 180, 212, 178,
+                59, 70, 79, 213, 58, 149, 177, 23, 184, 15, 43, 78, 56, 235,
+            ]),
+        }
+    }
+
+    // from https://stackoverflow.com/a/50072164/711863
+    pub fn human_readable_time(time_nano: u64) -> String {
+        let timestamp = (time_nano / 1_000_000_000)
+            .to_string()
+            .parse::<i64>()
+            .unwrap();
+        let naive = NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
+        let datetime: DateTime<Utc> = DateTime::from_utc(naive, Utc);
+        let newdate = datetime.format("%Y-%m-%d %H:%M:%S");
+        // Print the newly formatted date and time
+        newdate.to_string()
+    }
+
+    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .signer_account_pk(b"ed25519:4ZhGmuKTfQn9ZpHCQVRwEr4JnutL8Uu3kArfxEqksfVM".to_vec())
+            .predecessor_account_id(predecessor_account_id)
+            .block_index(BLOCK_START_BLOCK)
+            .block_timestamp(BLOCK_START_TS);
+        builder
+    }
+
+    #[test]
+    fn test_contract_new() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+    }
+
+    #[test]
+    fn test_task_create() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        let task_id = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+
+        let daily_task = get_sample_task_hr();
+        assert_eq!(contract.get_task(task_id), daily_task);
+    }
+
+    #[test]
+    #[should_panic(expected = "Create task paused")]
+    fn test_task_create_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(false).build());
+        contract.update_settings_with(Settings {
+            paused: Some(true),
+            ..Default::default()
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Cadence string invalid")]
+    fn test_task_create_bad_cadence() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "raspberry_oat_milk")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Maximum gas allocation exceeded")]
+    fn test_task_too_much_gas() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(270_000_000_000_000),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Function id invalid")]
+    fn test_task_create_bad_function_id() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000040000000200 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(0), "callback_for_proxy_call", "0 0 */1 * * *")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Creator invalid")]
+    fn test_task_create_bad_contract_id() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(6000000000040000000200 + storage_fee(&contract))
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(0)),
+            gas: Some(20000000000000),
+            ..TaskArgs::new(accounts(0), "tick", "0 0 * * * *")
+        });
+    }
+
+    #[test]
+    fn test_task_create_okay_contract_id() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000040000000200 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(0), "tick", "0 0 */1 * * *")
+        });
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Not enough task balance to execute job, need at least 500000000020000100000"
+    )]
+    fn test_task_create_deposit_not_enuf() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100000)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Not enough task balance to execute job, need at least 1000000000040000200000"
+    )]
+    fn test_task_create_deposit_not_enuf_recurring() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100000)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+    }
+
+    // NOTE: Useless when agent fee is higher than base gas
+    // #[test]
+    // #[should_panic(expected = "Gas minimum has not been met")]
+    // fn test_task_create_gas_min() {
+    //     let mut context = get_context(accounts(1));
+    //     testing_env!(context.build());
+    //     let mut contract = Contract::new();
+    //     testing_env!(context.is_view(false).attached_deposit(206000000000000000).build());
+    //     contract.create_task(
+    //         accounts(3),
+    //         "increment".to_string(),
+    //         "0 0 */1 * * *".to_string(),
+    //         Some(true),
+    //         Some(U128::from(100000000000000000)),
+    //         Some(0),
+    //         None,
+    //         None,
+    //         None,
+    //     );
+    // }
+
+    #[test]
+    fn test_task_create_slot_schedule() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+
+        // Move forward time and blocks to get more accurate bps
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .block_timestamp(BLOCK_START_TS + (6 * NANO))
+            .block_index(BLOCK_START_BLOCK + 6)
+            .build());
+
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context.is_view(true).build());
+        let slot = contract
+            .slots
+            .get(&1624151520000000000)
+            .expect("Should have something here");
+        assert_eq!(
+            slot.get(0).unwrap(),
+            [
+                110, 208, 206, 90, 101, 232, 173, 64, 29, 53, 128, 236, 199, 102, 202, 125, 75,
+                162, 235, 254, 94, 145, 121, 8, 160, 199, 94, 146, 137, 128, 165, 81
+            ]
+        );
+    }
+
+    // TODO: Finish
+    // #[test]
+    // fn test_task_proxy() {
+    //     let mut context = get_context(accounts(1));
+    //     testing_env!(context.build());
+    //     let mut contract = Contract::new();
+    //     testing_env!(context.is_view(false).attached_deposit(6000000000000).build());
+    //     contract.create_task(
+    //         accounts(3),
+    //         "increment".to_string(),
+    //         "*/10 * * * * *".to_string(),
+    //         Some(false),
+    //         None,
+    //         None,
+    //         None,
+    //         None,
+    //         None,
+    //     );
+    //     testing_env!(context.is_view(false).build());
+    //     contract.register_agent(None);
+
+    //     testing_env!(context.is_view(true).block_index(1260).build());
+    //     assert!(contract.get_all_tasks(None).len() > 0);
+    //     testing_env!(context.is_view(false).build());
+    //     contract.proxy_call(None);
+    //     assert!(contract.get_all_tasks(None).is_empty());
+    // }
+
+    #[test]
+    fn test_task_proxy_callback() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+
+        // Exited tasks are not rescheduled
+        contract.callback_for_proxy_call(vec![0, 1, 2, 3], U128::from(123400), None, None);
+        assert_eq!(contract.slots.len(), 0);
+    }
+
+    #[test]
+    fn test_task_proxy_call_failed_not_recurring() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+        assert!(contract.get_tasks(None, None, None).is_empty());
+
+        // The task exited, its callback still records the failed call
+        context
+            .predecessor_account_id(accounts(0))
+            .signer_account_id(accounts(0));
+        set_promise_result(&context, PromiseResult::Failed);
+        contract.callback_for_proxy_call(
+            task_hash.0,
+            U128::from(contract.get_slot_id(None)),
+            Some(accounts(4).to_string()),
+            None,
+        );
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_tasks_executed.0, 1);
+        assert_eq!(agent.total_failed_calls.0, 1);
+        assert_eq!(contract.slots.len(), 0);
+        let stats = contract.get_agents_stats();
+        assert_eq!(stats.total_tasks_executed.0, 1);
+        assert_eq!(stats.total_failed_calls.0, 1);
+        assert_eq!(stats.total_earnings, agent.total_earnings);
+    }
+
+    /// Executes a one-off task declaring a gas estimate of 50 out of 200, settled by the given call result
+    fn run_task_gas_estimate(result: PromiseResult) -> Contract {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            gas_estimate: Some(50),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+
+        // Agent is paid for the estimate, the task keeps the held gas (15000000000) until the callback
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_earnings.0, 500000000005000000000);
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.total_deposit.0, 500000000015000000000);
+        assert_eq!(
+            contract.task_balance_total,
+            500000000015000000000 + storage_fee(&contract)
+        );
+
+        context
+            .predecessor_account_id(accounts(0))
+            .signer_account_id(accounts(0));
+        set_promise_result(&context, result);
+        contract.callback_for_proxy_call(
+            task_hash.0,
+            U128::from(contract.get_slot_id(None)),
+            Some(accounts(4).to_string()),
+            Some(U128::from(15000000000)),
+        );
+        contract
+    }
+
+    #[test]
+    fn test_task_gas_estimate_refund() {
+        let contract = run_task_gas_estimate(PromiseResult::Successful(vec![]));
+
+        // Held gas is refunded to the task, then to the owner as the task exits
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_earnings.0, 500000000005000000000);
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        assert_eq!(contract.task_balance_total, 0);
+    }
+
+    #[test]
+    fn test_task_gas_estimate_failed() {
+        let contract = run_task_gas_estimate(PromiseResult::Failed);
+
+        // Failed calls may have used all of the gas, the agent gets the held gas
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_earnings.0, 500000000020000000000);
+        assert_eq!(agent.total_failed_calls.0, 1);
+        assert_eq!(
+            contract.agent_rewards_total,
+            AGENT_STORAGE_FEE + 500000000020000000000
+        );
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        assert_eq!(contract.task_balance_total, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Gas estimate exceeds task gas")]
+    fn test_task_create_gas_estimate_too_high() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            gas: Some(200),
+            gas_estimate: Some(201),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+    }
+
+    #[test]
+    fn test_task_proxy_call_agent() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+
+        // Register an agent, then move to a slot where the task is ready
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+
+        // Agent gets the gas & agent fee, non-recurring task is exited
+        testing_env!(context.is_view(true).build());
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE + 500000000020000000000);
+        assert_eq!(agent.total_tasks_executed.0, 1);
+        assert_eq!(agent.total_earnings.0, 500000000020000000000);
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        assert_eq!(contract.slots.len(), 0);
+    }
+
+    #[test]
+    fn test_task_proxy_call_empty_slot() {
+        let mut context = get_context(accounts(4));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .build());
+        contract.register_agent(None);
+        testing_env!(context.attached_deposit(0).build());
+        contract.proxy_call(None);
+
+        // Rewarded for checking the slot, without counting as an execution
+        testing_env!(context.is_view(true).build());
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE + AGENT_BASE_FEE);
+        assert_eq!(agent.total_tasks_executed.0, 0);
+        assert_eq!(agent.total_empty_slot_rewards.0, 1);
+        assert_eq!(agent.total_earnings.0, AGENT_BASE_FEE);
+    }
+
+    #[test]
+    fn test_task_proxy_call_agent_recurring() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+
+        // Task stays, charged for 1 execution, waiting on callback to be rescheduled
+        let task = contract.get_task(task_hash.clone());
+        assert_eq!(task.total_deposit.0, ONE_NEAR - 500000000020000000100);
+        assert_eq!(contract.slots.len(), 0);
+
+        let current_slot = contract.get_slot_id(None);
+        contract.callback_for_proxy_call(task_hash.0.clone(), U128::from(current_slot), None, None);
+        let next_slot = contract.slots.min().expect("Task should be rescheduled");
+        assert!(next_slot > current_slot);
+        assert_eq!(
+            contract.slots.get(&next_slot).unwrap().to_vec(),
+            vec![task_hash.0]
+        );
+    }
+
+    #[test]
+    fn test_task_proxy_call_owner() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call_owner();
+
+        // Same exit semantics, no agent gets rewarded
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        assert_eq!(contract.slots.len(), 0);
+        assert!(contract.get_agent(accounts(0).to_string()).is_none());
+    }
+
+    #[test]
+    fn test_task_execute_trigger_agent() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+
+        // Triggers execute within a callback, so the signer is not the agent
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        let current_slot = contract.get_slot_id(None);
+        let promise = contract.execute_task(
+            task_hash.0.clone(),
+            task,
+            Some(accounts(4).to_string()),
+            current_slot,
+        );
+        assert!(promise.is_some());
+
+        testing_env!(context.is_view(true).build());
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE + 500000000020000000000);
+        assert_eq!(agent.total_tasks_executed.0, 1);
+        assert!(contract.get_agent(accounts(1).to_string()).is_none());
+        let task = contract.get_task(task_hash);
+        assert_eq!(task.total_deposit.0, ONE_NEAR - 500000000020000000100);
+    }
+
+    #[test]
+    fn test_task_execute_not_enough_balance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+
+        // Drain the task below the cost of a single execution
+        let mut task = contract.tasks.get(&task_hash.0).unwrap();
+        task.total_deposit = U128::from(100);
+        contract.tasks.insert(&task_hash.0, &task);
+
+        let current_slot = contract.get_slot_id(None);
+        let promise = contract.execute_task(
+            task_hash.0.clone(),
+            task,
+            Some(accounts(4).to_string()),
+            current_slot,
+        );
+        assert!(promise.is_none());
+
+        // Task exited without paying the agent
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE);
+        assert_eq!(agent.total_tasks_executed.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Agent not registered")]
+    fn test_task_proxy_agent_not_registered() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .block_index(1260)
+            .attached_deposit(3000000000000300)
+            .prepaid_gas(300000000000)
+            .build());
+        contract.proxy_call(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Task execution paused")]
+    fn test_task_proxy_paused() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_settings_with(Settings {
+            paused: Some(true),
+            ..Default::default()
+        });
+        testing_env!(context.is_view(false).block_index(1260).build());
+        contract.proxy_call(None);
+    }
+
+    #[test]
+    fn test_task_priority_tip() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let create = |contract: &mut Contract, function_id: &str, tip: u128| {
+            contract.create_task_with(TaskArgs {
+                recurring: Some(false),
+                deposit: Some(U128::from(100)),
+                gas: Some(200),
+                priority_tip: Some(U128::from(tip)),
+                ..TaskArgs::new(accounts(3), function_id, "*/10 * * * * *")
+            })
+        };
+        let low_first = create(&mut contract, "increment", 0);
+        let high = create(&mut contract, "decrement", 1000);
+        let low_last = create(&mut contract, "reset", 0);
+
+        // Highest tip is popped first, then equal tips by most recent
+        let slot = contract.slots.min().unwrap();
+        assert_eq!(
+            contract.slots.get(&slot).unwrap().to_vec(),
+            vec![low_first.0, low_last.0, high.0.clone()]
+        );
+
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+
+        // Tip goes to the agent, on top of the fee
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(
+            agent.balance.0,
+            AGENT_STORAGE_FEE + 500000000020000000000 + 1000
+        );
+        assert!(contract.tasks.get(&high.0).is_none());
+        assert_eq!(contract.slots.get(&slot).unwrap().len(), 2);
+    }
+
+    /// Creates 3 tasks in the same slot and a single agent, then runs a batch at the given gas
+    fn run_task_batch(max: u8, prepaid_gas: Gas) -> (Contract, u128) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        for function_id in ["increment", "decrement", "reset"].iter() {
+            contract.create_task_with(TaskArgs {
+                recurring: Some(false),
+                deposit: Some(U128::from(100)),
+                gas: Some(200),
+                ..TaskArgs::new(accounts(3), function_id, "*/10 * * * * *")
+            });
+        }
+        let slot = contract.slots.min().unwrap();
+
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .prepaid_gas(prepaid_gas)
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call_batch(max, None);
+        (contract, slot)
+    }
+
+    #[test]
+    fn test_task_proxy_batch() {
+        let (contract, slot) = run_task_batch(2, MAX_NEAR_GAS);
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_tasks_executed.0, 2);
+        assert_eq!(contract.slots.get(&slot).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_task_proxy_batch_more_than_slot() {
+        let (contract, _) = run_task_batch(5, MAX_NEAR_GAS);
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_tasks_executed.0, 3);
+        assert_eq!(contract.slots.len(), 0);
+    }
+
+    #[test]
+    fn test_task_proxy_batch_gas_budget() {
+        // Every task after the first needs its gas, callback and proxy overhead left over
+        let (contract, slot) = run_task_batch(3, 150_000_000_000_000);
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.total_tasks_executed.0, 2);
+        assert_eq!(contract.slots.get(&slot).unwrap().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Batch must execute at least 1 task")]
+    fn test_task_proxy_batch_empty() {
+        run_task_batch(0, MAX_NEAR_GAS);
+    }
+
+    /// Runs a late execution of a "*/10" task, created at the start of the test block
+    fn run_late_task(catch_up: CatchUpPolicy) -> (Contract, Vec<u8>) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            catch_up: Some(catch_up),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+
+        // Task slot 1624151520000000000 is executed at slot 1624151760000000000,
+        // missing the slots 1624151580000000000 through 1624151760000000000
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (300 * NANO))
+            .build());
+        contract.proxy_call(None);
+        let current_slot = contract.get_slot_id(None);
+        contract.callback_for_proxy_call(task_hash.0.clone(), U128::from(current_slot), None, None);
+        (contract, task_hash.0)
+    }
+
+    #[test]
+    fn test_task_catch_up_skip() {
+        let (contract, task_hash) = run_late_task(CatchUpPolicy::Skip);
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.missed_occurrences.0, 4);
+        assert_eq!(task.catch_up_pending.0, 0);
+        assert_eq!(task.slot.0, 1624151820000000000);
+    }
+
+    #[test]
+    fn test_task_catch_up_run_once() {
+        let (mut contract, task_hash) = run_late_task(CatchUpPolicy::RunOnce);
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.missed_occurrences.0, 4);
+        assert_eq!(task.catch_up_pending.0, 1);
+        assert_eq!(task.slot.0, 1624151760000000000);
+
+        // Back to the cadence after the catch up execution
+        contract.callback_for_proxy_call(
+            task_hash.clone(),
+            U128::from(1624151760000000000),
+            None,
+            None,
+        );
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.catch_up_pending.0, 0);
+        assert_eq!(task.slot.0, 1624151820000000000);
+        assert_eq!(contract.slots.len(), 1);
+    }
+
+    #[test]
+    fn test_task_catch_up_run_all_missed() {
+        let (mut contract, task_hash) = run_late_task(CatchUpPolicy::RunAllMissed);
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.missed_occurrences.0, 4);
+        assert_eq!(task.catch_up_pending.0, 4);
+        assert_eq!(task.slot.0, 1624151760000000000);
+
+        for pending in (0..4).rev() {
+            contract.callback_for_proxy_call(
+                task_hash.clone(),
+                U128::from(1624151760000000000),
+                None,
+                None,
+            );
+            let task = contract.tasks.get(&task_hash).unwrap();
+            assert_eq!(task.catch_up_pending.0, pending);
+        }
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.missed_occurrences.0, 4);
+        assert_eq!(task.slot.0, 1624151820000000000);
+    }
+
+    /// Executes a "*/10" task 4 slots late, with the given max delay (in seconds)
+    fn run_task_with_max_delay(recurring: bool, max_delay: u64) -> (Contract, Vec<u8>) {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(recurring),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            max_delay: Some(U64::from(max_delay * NANO)),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .block_timestamp(BLOCK_START_TS + (300 * NANO))
+            .build());
+        contract.proxy_call(None);
+        (contract, task_hash.0)
+    }
+
+    #[test]
+    fn test_task_max_delay_skip() {
+        let (contract, task_hash) = run_task_with_max_delay(true, 60);
+
+        // Only the skip fee is charged, and paid to the agent
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.total_deposit.0, ONE_NEAR - AGENT_BASE_FEE);
+        assert_eq!(task.missed_occurrences.0, 1);
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE + AGENT_BASE_FEE);
+        assert_eq!(agent.total_tasks_executed.0, 0);
+        assert_eq!(agent.total_earnings.0, AGENT_BASE_FEE);
+
+        // Rescheduled to the next occurrence
+        assert_eq!(task.slot.0, 1624151820000000000);
+        assert_eq!(
+            contract.slots.get(&1624151820000000000).unwrap().to_vec(),
+            vec![task_hash]
+        );
+        assert_eq!(contract.slots.len(), 1);
+    }
+
+    #[test]
+    fn test_task_max_delay_skip_not_recurring() {
+        let (contract, task_hash) = run_task_with_max_delay(false, 60);
+        assert!(contract.tasks.get(&task_hash).is_none());
+        assert_eq!(contract.slots.len(), 0);
+        assert_eq!(contract.task_balance_total, 0);
+    }
+
+    #[test]
+    fn test_task_max_delay_within() {
+        let (contract, task_hash) = run_task_with_max_delay(true, 600);
+        let task = contract.tasks.get(&task_hash).unwrap();
+        assert_eq!(task.total_deposit.0, ONE_NEAR - 500000000020000000100);
+        assert_eq!(task.missed_occurrences.0, 0);
+    }
+
+    #[test]
+    fn test_task_proxy_gas_price_samples() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        assert_eq!(contract.gas_price, GAS_BASE_PRICE);
+
+        // Default bounds keep reports between the base price & the ceiling
+        contract.record_gas_price(&accounts(2).to_string(), GAS_BASE_PRICE * 50);
+        assert_eq!(contract.gas_price, GAS_PRICE_CEILING);
+        contract.record_gas_price(&accounts(2).to_string(), 1);
+        assert_eq!(contract.gas_price, GAS_BASE_PRICE);
+
+        // Within other owner bounds, an agent only holds 1 sample however often it reports
+        contract.update_settings_with(Settings {
+            gas_price_floor: Some(U128::from(GAS_BASE_PRICE / 2)),
+            gas_price_ceiling: Some(U128::from(GAS_BASE_PRICE * 4)),
+            ..Default::default()
+        });
+        for _ in 0..GAS_PRICE_SAMPLES {
+            contract.record_gas_price(&accounts(2).to_string(), GAS_BASE_PRICE * 50);
+        }
+        let (gas_price, _, _, samples) = contract.get_gas_price_info();
+        assert_eq!(samples, vec![U128::from(GAS_BASE_PRICE * 4)]);
+        assert_eq!(gas_price.0, GAS_BASE_PRICE * 4);
+
+        // Other agents outvote it, reports are clamped into the bounds & the median is charged
+        contract.record_gas_price(&accounts(3).to_string(), GAS_BASE_PRICE);
+        contract.record_gas_price(&accounts(4).to_string(), 1);
+        let (gas_price, _, _, samples) = contract.get_gas_price_info();
+        assert_eq!(
+            samples,
+            vec![
+                U128::from(GAS_BASE_PRICE * 4),
+                U128::from(GAS_BASE_PRICE),
+                U128::from(GAS_BASE_PRICE / 2)
+            ]
+        );
+        assert_eq!(gas_price.0, GAS_BASE_PRICE);
+    }
+
+    #[test]
+    fn test_task_proxy_gas_price_needs_active_execution() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        for function_id in ["increment", "decrement"].iter() {
+            testing_env!(context
+                .is_view(false)
+                .attached_deposit(ONE_NEAR + storage_fee(&contract))
+                .build());
+            contract.create_task_with(TaskArgs::new(accounts(3), function_id, "*/10 * * * * *"));
+        }
+        // 2 active agents let any agent execute late tasks, the 3rd stays pending
+        contract.update_settings_with(Settings {
+            agent_task_ratio: Some(vec![U64::from(1), U64::from(1)]),
+            ..Default::default()
+        });
+        for agent in [accounts(4), accounts(5), accounts(2)].iter() {
+            testing_env!(context
+                .attached_deposit(AGENT_STORAGE_FEE)
+                .predecessor_account_id(agent.clone())
+                .build());
+            contract.register_agent(None);
+            contract.tick();
+        }
+        assert_eq!(contract.agent_active_queue.len(), 2);
+        let report = Some(U128::from(GAS_BASE_PRICE * 2));
+
+        // No task executed, no sample
+        testing_env!(context
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.proxy_call(report);
+        assert!(contract.gas_price_samples.is_empty());
+
+        // A pending agent executing a late task, no sample
+        testing_env!(context
+            .block_timestamp(BLOCK_START_TS + (300 * NANO))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.proxy_call(report);
+        assert_eq!(
+            contract
+                .get_agent(accounts(2).to_string())
+                .unwrap()
+                .total_tasks_executed
+                .0,
+            1
+        );
+        assert!(contract.gas_price_samples.is_empty());
+
+        // The active agent executing a task, sampled
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.proxy_call(report);
+        assert_eq!(
+            contract
+                .get_agent(accounts(4).to_string())
+                .unwrap()
+                .total_tasks_executed
+                .0,
+            1
+        );
+        assert_eq!(
+            contract.gas_price_samples,
+            vec![(accounts(4).to_string(), GAS_BASE_PRICE * 2)]
+        );
+        assert_eq!(contract.gas_price, GAS_BASE_PRICE * 2);
+    }
+
+    #[test]
+    fn test_task_remove() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 100 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+
+        testing_env!(context.is_view(false).build());
+        contract.remove_task(task_hash);
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_tasks(None, None, None).len(), 0);
+    }
+
+    #[test]
+    fn test_task_remove_after_cadence_moved() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR * 100 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        assert_eq!(contract.slots.len(), 1);
+
+        // Two hours later, the cadence points at a different slot than the one stored
+        testing_env!(context
+            .block_timestamp(BLOCK_START_TS + (7200 * NANO))
+            .build());
+        contract.remove_task(task_hash);
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.slots.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can remove their task.")]
+    fn test_task_remove_not_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        assert!(contract.get_tasks(None, None, None).is_empty());
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.remove_task(task_hash);
+    }
+
+    #[test]
+    #[should_panic(expected = "No task found by hash")]
+    fn test_task_remove_no_task() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.remove_task(Base64VecU8::from(vec![0, 1, 2, 3]));
+    }
+
+    /// Creates an hourly task for "function_id", funded for 2 executions on top of its storage
+    fn create_limited_task(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        function_id: &str,
+    ) -> Base64VecU8 {
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(500000000020000000100 * 2 + storage_fee(contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), function_id, "0 0 */1 * * *")
+        })
+    }
+
+    #[test]
+    fn test_task_storage_fee() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let fee = storage_fee(&contract);
+        assert!(fee > 0);
+        assert_eq!(
+            contract.get_task_storage_fee(Some(Base64VecU8::from(vec![1, 2, 3]))),
+            (
+                U128::from(fee + env::storage_byte_cost() * 3),
+                U64::from(MAX_TASKS_PER_OWNER),
+                U64::from(MAX_TASKS_PER_SLOT)
+            )
+        );
+
+        // Storage is kept apart from the task balance, and refunded on exit
+        let task_hash = create_limited_task(&mut context, &mut contract, "increment");
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.storage_deposit.0, fee);
+        assert_eq!(task.total_deposit.0, 500000000020000000100 * 2);
+        assert_eq!(contract.task_balance_total, 500000000020000000100 * 2 + fee);
+        contract.remove_task(task_hash);
+        assert_eq!(contract.task_balance_total, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough task balance to execute job")]
+    fn test_task_storage_fee_not_enuf() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(500000000020000000100 * 2)
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many tasks for this owner")]
+    fn test_task_max_per_owner() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.max_tasks_per_owner = 2;
+        create_limited_task(&mut context, &mut contract, "increment");
+        create_limited_task(&mut context, &mut contract, "decrement");
+        create_limited_task(&mut context, &mut contract, "reset");
+    }
+
+    #[test]
+    fn test_task_max_per_slot() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.max_tasks_per_slot = 2;
+        let first = create_limited_task(&mut context, &mut contract, "increment");
+        let second = create_limited_task(&mut context, &mut contract, "decrement");
+        let third = create_limited_task(&mut context, &mut contract, "reset");
+
+        // Full slots spill into their sub-slots
+        let slot = contract.tasks.get(&first.0).unwrap().slot.0;
+        assert_eq!(contract.tasks.get(&second.0).unwrap().slot.0, slot);
+        assert_eq!(contract.tasks.get(&third.0).unwrap().slot.0, slot + 1);
+        assert_eq!(
+            contract.slots.get(&slot).unwrap().to_vec(),
+            vec![first.0.clone(), second.0.clone()]
+        );
+        assert_eq!(
+            contract.slots.get(&(slot + 1)).unwrap().to_vec(),
+            vec![third.0.clone()]
+        );
+
+        // Sub-slots run within their slot, the lowest one first
+        assert_eq!(contract.get_slot_ballpark(slot), slot);
+        assert_eq!(contract.get_slot_task_count(slot), 3);
+        contract.remove_slot_task(slot + 1, &third.0);
+        assert_eq!(contract.get_slot_ballpark(slot), slot);
+        assert_eq!(contract.get_slot_task_count(slot), 2);
+
+        // Removing keeps the order of the remaining tasks
+        contract.remove_slot_task(slot, &first.0);
+        assert_eq!(
+            contract.slots.get(&slot).unwrap().to_vec(),
+            vec![second.0.clone()]
+        );
+        contract.remove_slot_task(slot, &second.0);
+        assert_eq!(contract.slots.len(), 0);
+    }
+
+    #[test]
+    fn test_task_max_per_slot_drain_order() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.max_tasks_per_slot = 2;
+        let first = create_limited_task(&mut context, &mut contract, "increment");
+        let second = create_limited_task(&mut context, &mut contract, "decrement");
+        let third = create_limited_task(&mut context, &mut contract, "reset");
+        let slot = contract.tasks.get(&first.0).unwrap().slot.0;
+
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+
+        // Agents are given the tasks of all sub-slots
+        testing_env!(context
+            .is_view(true)
+            .attached_deposit(0)
+            .block_timestamp(slot as u64)
+            .build());
+        assert_eq!(contract.get_agent_tasks(accounts(4)).0, U64::from(3));
+        assert_eq!(
+            contract.check_agent_can_execute(accounts(4).to_string(), 3),
+            (true, 0, 3)
+        );
+
+        // The first filled sub-slot is drained before its spill
+        assert_eq!(
+            contract.slots.get(&slot).unwrap().to_vec(),
+            vec![first.0.clone(), second.0.clone()]
+        );
+        testing_env!(context.is_view(false).build());
+        contract.proxy_call(None);
+        assert_eq!(
+            contract.slots.get(&slot).unwrap().to_vec(),
+            vec![first.0.clone()]
+        );
+        contract.proxy_call(None);
+        assert!(contract.slots.get(&slot).is_none());
+        assert_eq!(
+            contract.slots.get(&(slot + 1)).unwrap().to_vec(),
+            vec![third.0.clone()]
+        );
+        assert_eq!(contract.get_slot_ballpark(slot), slot + 1);
+        assert_eq!(contract.get_slot_task_count(slot), 1);
+        testing_env!(context.is_view(true).build());
+        assert_eq!(contract.get_agent_tasks(accounts(4)).0, U64::from(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "No task found by hash")]
+    fn test_task_refill_no_task() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.refill_balance(Base64VecU8::from(vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_task_refill_sponsor() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        let cost: u128 = 500000000020000000100;
+
+        // Anyone can add balance, tracked per account
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(cost * 4)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.refill_balance(task_hash.clone());
+        contract.refill_balance(task_hash.clone());
+
+        // The first refill pays the storage of the sponsor contribution
+        let contribution_fee = contract.contribution_storage_fee();
+        assert_eq!(
+            contract.tasks.get(&task_hash.0).unwrap().storage_deposit.0,
+            storage_fee(&contract) + contribution_fee
+        );
+        let task = contract.get_task(task_hash.clone());
+        assert_eq!(task.total_deposit.0, cost * 12 - contribution_fee);
+        assert_eq!(
+            task.contributions,
+            vec![
+                Contribution {
+                    account_id: accounts(1).to_string(),
+                    amount: U128::from(cost * 4),
+                    storage_deposit: U128::from(0),
+                },
+                Contribution {
+                    account_id: accounts(4).to_string(),
+                    amount: U128::from(cost * 8 - contribution_fee),
+                    storage_deposit: U128::from(contribution_fee),
+                }
+            ]
+        );
+        assert_eq!(
+            contract.task_balance_total,
+            cost * 12 + storage_fee(&contract)
+        );
+
+        // The owner can only withdraw their own share, leaving sponsor shares unchanged
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.withdraw_task_deposit(task_hash.clone(), None);
+        let task = contract.get_task(task_hash.clone());
+        assert_eq!(task.total_deposit.0, cost * 8 - contribution_fee);
+        assert_eq!(task.contributions[0].amount.0, 0);
+        assert_eq!(
+            pro_rata(
+                task.total_deposit.0,
+                task.contributions[1].amount.0,
+                cost * 8 - contribution_fee
+            ),
+            cost * 8 - contribution_fee
+        );
+    }
+
+    #[test]
+    fn test_task_exit_refunds_sponsor_storage() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        let cost: u128 = 500000000020000000100;
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(cost * 4)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.refill_balance(task_hash.clone());
+
+        // The sponsor gets back its share & its contribution storage, the owner the rest
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.remove_task(task_hash);
+        assert_eq!(
+            get_transfers(),
+            vec![
+                (accounts(4).to_string(), cost * 4),
+                (accounts(1).to_string(), cost * 4 + storage_fee(&contract)),
+            ]
+        );
+        assert_eq!(contract.task_balance_total, 0);
+    }
+
+    #[test]
+    fn test_task_accept_ownership_refunds_sponsor_storage() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        let cost: u128 = 500000000020000000100;
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(cost * 4)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.refill_balance(task_hash.clone());
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.transfer_task_ownership(task_hash.clone(), Some(accounts(4)));
+
+        // The sponsor entry becomes the owner entry, paid with the task storage
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.accept_task_ownership(task_hash.clone());
+        let contribution_fee = contract.contribution_storage_fee();
+        assert_eq!(
+            get_transfers(),
+            vec![(accounts(4).to_string(), contribution_fee)]
+        );
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.storage_deposit.0, storage_fee(&contract));
+        assert_eq!(
+            task.contributions,
+            vec![Contribution {
+                account_id: accounts(4).to_string(),
+                amount: U128::from(cost * 8 - contribution_fee),
+                storage_deposit: U128::from(0),
+            }]
+        );
+        assert_eq!(
+            contract.task_balance_total,
+            cost * 8 - contribution_fee + storage_fee(&contract)
         );
-
-        // if out of balance or non-recurring, exit callback
-        if !task.recurring || call_total_balance > task.total_deposit.0 {
-            // Process task exit, if no future task can execute
-            self.exit_task(hash);
-            env::promise_return(promise_first);
-        } else {
-            // if recurring, callback for scheduling
-            let promise_second = env::promise_then(
-                promise_first,
-                env::current_account_id(),
-                b"callback_for_proxy_call",
-                json!({
-                    "task_hash": hash,
-                    "current_slot": U128::from(current_slot)
-                })
-                .to_string()
-                .as_bytes(),
-                0,
-                GAS_FOR_CALLBACK,
-            );
-            env::promise_return(promise_second);
-        }
     }
 
-    /// Returns the base amount required to execute 1 task
-    /// NOTE: this is not the final used amount, just the user-specified amount total needed
-    pub fn task_balance_uses(&self, task: &Task) -> u128 {
-        task.deposit.0 + (u128::from(task.gas) * self.gas_price) + self.agent_fee
+    #[test]
+    #[should_panic(expected = "New sponsors need to attach at least")]
+    fn test_task_refill_sponsor_dust() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.refill_balance(task_hash);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::json_types::ValidAccountId;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, MockedBlockchain};
-
-    use chrono::prelude::DateTime;
-    use chrono::Utc;
-    use chrono::*;
-    use cron_schedule::Schedule;
-    use std::str::FromStr;
-
-    const BLOCK_START_BLOCK: u64 = 52_201_040;
-    const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
-
-    pub fn get_sample_task() -> Task {
-        Task {
-            owner_id: String::from("bob"),
-            contract_id: String::from("danny"),
-            function_id: String::from("increment"),
-            cadence: String::from("0 0 */1 * * *"),
-            recurring: false,
-            total_deposit: U128::from(1000000000020000000100),
-            deposit: U128::from(100),
-            gas: 200,
-            arguments: Base64VecU8::from(vec![]),
+    #[test]
+    #[should_panic(expected = "Too many sponsors for this task")]
+    fn test_task_refill_too_many_sponsors() {
+        use std::convert::TryFrom;
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        for i in 0..MAX_TASK_CONTRIBUTORS {
+            let sponsor = format!("sponsor{}.testnet", i);
+            testing_env!(context
+                .is_view(false)
+                .attached_deposit(ONE_NEAR)
+                .signer_account_id(ValidAccountId::try_from(sponsor.clone()).unwrap())
+                .predecessor_account_id(ValidAccountId::try_from(sponsor).unwrap())
+                .build());
+            contract.refill_balance(task_hash.clone());
         }
     }
-    pub fn get_sample_task_hr() -> TaskHumanFriendly {
-        TaskHumanFriendly {
-            owner_id: String::from("bob"),
-            contract_id: String::from("danny"),
-            function_id: String::from("increment"),
-            cadence: String::from("0 0 */1 * * *"),
-            recurring: false,
-            total_deposit: U128::from(1000000000020000000100),
-            deposit: U128::from(100),
-            gas: 200,
-            arguments: Base64VecU8::from(vec![]),
-            hash: Base64VecU8(vec![
-                25, 109, 16, 117, 147, 91, 137, 42, 231, 234, 13, 62, 155, 180, 27, 180, 212, 178,
-                59, 70, 79, 213, 58, 149, 177, 23, 184, 15, 43, 78, 56, 235,
-            ]),
-        }
+
+    #[test]
+    fn test_pro_rata() {
+        assert_eq!(pro_rata(100, 1, 3), 33);
+        assert_eq!(pro_rata(100, 0, 3), 0);
+        assert_eq!(pro_rata(100, 5, 0), 0);
+        // Would overflow as "amount * part"
+        assert_eq!(
+            pro_rata(ONE_NEAR * 1000, ONE_NEAR * 300, ONE_NEAR * 900),
+            333333333333333333333333333
+        );
     }
 
-    // from https://stackoverflow.com/a/50072164/711863
-    pub fn human_readable_time(time_nano: u64) -> String {
-        let timestamp = (time_nano / 1_000_000_000)
-            .to_string()
-            .parse::<i64>()
-            .unwrap();
-        let naive = NaiveDateTime::from_timestamp(timestamp, 0);
-        let datetime: DateTime<Utc> = DateTime::from_utc(naive, Utc);
-        let newdate = datetime.format("%Y-%m-%d %H:%M:%S");
-        // Print the newly formatted date and time
-        newdate.to_string()
+    /// Creates a task funded for 4 executions, returning its hash
+    fn create_withdraw_task(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+    ) -> Base64VecU8 {
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(500000000020000000100 * 4 + storage_fee(contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        })
     }
 
-    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .current_account_id(accounts(0))
-            .signer_account_id(predecessor_account_id.clone())
-            .signer_account_pk(b"ed25519:4ZhGmuKTfQn9ZpHCQVRwEr4JnutL8Uu3kArfxEqksfVM".to_vec())
-            .predecessor_account_id(predecessor_account_id)
-            .block_index(BLOCK_START_BLOCK)
-            .block_timestamp(BLOCK_START_TS);
-        builder
+    #[test]
+    fn test_task_withdraw_balance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        let cost: u128 = 500000000020000000100;
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+
+        testing_env!(context.is_view(false).attached_deposit(1).build());
+        contract.withdraw_task_deposit(task_hash.clone(), Some(U128::from(cost)));
+        assert_eq!(
+            contract.get_task(task_hash.clone()).total_deposit.0,
+            cost * 3
+        );
+        assert_eq!(
+            contract.task_balance_total,
+            cost * 3 + storage_fee(&contract)
+        );
+
+        // Defaults to all but 1 execution
+        contract.withdraw_task_deposit(task_hash.clone(), None);
+        assert_eq!(contract.get_task(task_hash).total_deposit.0, cost);
+        assert_eq!(contract.task_balance_total, cost + storage_fee(&contract));
     }
 
     #[test]
-    fn test_contract_new() {
+    #[should_panic(
+        expected = "Task balance must cover 1 more execution, can withdraw up to 1500000000060000000300"
+    )]
+    fn test_task_withdraw_balance_too_much() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
-        let contract = Contract::new();
-        testing_env!(context.is_view(true).build());
-        assert!(contract.get_tasks(None, None, None).is_empty());
+        let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        testing_env!(context.is_view(false).attached_deposit(1).build());
+        contract.withdraw_task_deposit(task_hash, Some(U128::from(500000000020000000100 * 3 + 1)));
     }
 
     #[test]
-    fn test_task_create() {
+    #[should_panic(expected = "Only owner can withdraw their task balance")]
+    fn test_task_withdraw_balance_not_owner() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        testing_env!(context.is_view(true).build());
-        assert!(contract.get_tasks(None, None, None).is_empty());
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
             .build());
-        let task_id = contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
-        );
-
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
-
-        let daily_task = get_sample_task_hr();
-        assert_eq!(contract.get_task(task_id), daily_task);
+        contract.withdraw_task_deposit(task_hash, None);
     }
 
     #[test]
-    #[should_panic(expected = "Create task paused")]
-    fn test_task_create_paused() {
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_task_withdraw_deposit_no_yocto() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        testing_env!(context.is_view(false).build());
-        contract.update_settings(None, None, Some(true), None, None, None, None, None, None);
-        testing_env!(context
-            .is_view(false)
-            .attached_deposit(1000000000020000000100)
-            .build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(100)),
-            Some(200),
-            None,
-        );
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.withdraw_task_deposit(task_hash, None);
     }
 
     #[test]
-    #[should_panic(expected = "Cadence string invalid")]
-    fn test_task_create_bad_cadence() {
+    fn test_task_transfer_ownership() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(contract.delegate_storage_fee())
             .build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "raspberry_oat_milk".to_string(),
-            Some(true),
-            Some(U128::from(100)),
-            Some(200),
-            None,
+        contract.update_task_delegates(task_hash.clone(), vec![accounts(4)]);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.transfer_task_ownership(task_hash.clone(), Some(accounts(2)));
+        assert_eq!(
+            contract.get_task(task_hash.clone()).pending_owner_id,
+            Some(accounts(2).to_string())
+        );
+
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        let storage_deposit = contract.tasks.get(&task_hash.0).unwrap().storage_deposit.0;
+        contract.accept_task_ownership(task_hash.clone());
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.owner_id, accounts(2).to_string());
+        assert_eq!(task.pending_owner_id, None);
+        assert!(task.delegates.is_empty());
+        // Delegates storage went back to the previous owner
+        assert_eq!(
+            task.storage_deposit.0,
+            storage_deposit - contract.delegate_storage_fee()
+        );
+        assert_eq!(task.contributions[0].account_id, accounts(2).to_string());
+        assert!(contract.task_owners.get(&accounts(1).to_string()).is_none());
+        assert_eq!(
+            contract
+                .get_tasks_by_owner(accounts(2).to_string(), None, None)
+                .len(),
+            1
         );
+
+        // The new owner can now withdraw
+        testing_env!(context.is_view(false).attached_deposit(1).build());
+        contract.withdraw_task_deposit(task_hash, None);
     }
 
     #[test]
-    #[should_panic(expected = "Maximum gas allocation exceeded")]
-    fn test_task_too_much_gas() {
+    fn test_task_transfer_ownership_removes_triggers() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(contract.trigger_storage_usage as u128)
             .build());
-        contract.create_task(
+        let trigger_hash = contract.create_trigger(
             accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(100)),
-            Some(270_000_000_000_000),
+            "should_increment".to_string(),
+            task_hash.clone(),
             None,
         );
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.transfer_task_ownership(task_hash.clone(), Some(accounts(2)));
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.accept_task_ownership(task_hash.clone());
+
+        // The previous owner trigger is dropped instead of executing the task
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.proxy_conditional_call(trigger_hash.clone());
+        assert!(contract.triggers.get(&trigger_hash.0).is_none());
+        assert_eq!(
+            contract.get_task(task_hash).total_deposit.0,
+            500000000020000000100 * 4
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Function id invalid")]
-    fn test_task_create_bad_function_id() {
+    fn test_task_trigger_paused_rewards_agent() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.pause_task(task_hash.clone());
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000040000000200)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
             .build());
-        contract.create_task(
-            accounts(0),
-            "callback_for_proxy_call".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(100)),
-            Some(200),
-            None,
+        contract.register_agent(None);
+
+        // The trigger returns true, the paused task is not executed but the agent is rewarded
+        context
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .signer_account_id(accounts(0));
+        set_promise_result(
+            &context,
+            PromiseResult::Successful(b"[true, null]".to_vec()),
+        );
+        contract.proxy_conditional_callback(task_hash.clone(), accounts(4).to_string());
+        assert_eq!(
+            contract.get_task(task_hash).total_deposit.0,
+            500000000020000000100 * 4
         );
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(agent.balance.0, AGENT_STORAGE_FEE + AGENT_BASE_FEE);
+        assert_eq!(agent.total_tasks_executed.0, 0);
+        assert_eq!(agent.total_empty_slot_rewards.0, 1);
     }
 
     #[test]
-    #[should_panic(expected = "Creator invalid")]
-    fn test_task_create_bad_contract_id() {
-        let mut context = get_context(accounts(0));
+    #[should_panic(
+        expected = "Not enough gas attached for the trigger view & task execution, 105000000000200 required"
+    )]
+    fn test_task_trigger_not_enough_gas() {
+        let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(6000000000040000000200)
-            .predecessor_account_id(accounts(2))
-            .signer_account_id(accounts(2))
+            .attached_deposit(contract.trigger_storage_usage as u128)
             .build());
-        contract.create_task(
-            accounts(0),
-            "tick".to_string(),
-            "0 0 * * * *".to_string(),
-            Some(true),
-            Some(U128::from(0)),
-            Some(20000000000000),
-            None,
-        );
+        let trigger_hash =
+            contract.create_trigger(accounts(3), "should_increment".to_string(), task_hash, None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .prepaid_gas(100_000_000_000_000)
+            .build());
+        contract.proxy_conditional_call(trigger_hash);
     }
 
     #[test]
-    fn test_task_create_okay_contract_id() {
-        let mut context = get_context(accounts(0));
+    fn test_task_delegates_storage() {
+        let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        let storage_deposit = contract.tasks.get(&task_hash.0).unwrap().storage_deposit.0;
+        let balance_total = contract.task_balance_total;
+        let delegate_fee = contract.delegate_storage_fee();
+
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000040000000200)
+            .attached_deposit(delegate_fee * 2)
             .build());
-        contract.create_task(
-            accounts(0),
-            "tick".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(100)),
-            Some(200),
-            None,
+        contract.update_task_delegates(task_hash.clone(), vec![accounts(2), accounts(4)]);
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.delegates.len(), 2);
+        assert_eq!(task.storage_deposit.0, storage_deposit + delegate_fee * 2);
+        assert_eq!(
+            contract.task_balance_total,
+            balance_total + delegate_fee * 2
         );
 
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+        // Removing a delegate releases its storage
+        testing_env!(context.is_view(false).attached_deposit(0).build());
+        contract.update_task_delegates(task_hash.clone(), vec![accounts(4)]);
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.delegates, vec![accounts(4).to_string()]);
+        assert_eq!(task.storage_deposit.0, storage_deposit + delegate_fee);
+        assert_eq!(contract.task_balance_total, balance_total + delegate_fee);
     }
 
     #[test]
-    #[should_panic(
-        expected = "Not enough task balance to execute job, need at least 500000000020000100000"
-    )]
-    fn test_task_create_deposit_not_enuf() {
+    #[should_panic(expected = "Delegates storage payment of 680000000000000000000 required")]
+    fn test_task_delegates_storage_not_paid() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context.is_view(false).attached_deposit(0).build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100000)),
-            Some(200),
-            None,
-        );
+        contract.update_task_delegates(task_hash, vec![accounts(4)]);
     }
 
     #[test]
-    #[should_panic(
-        expected = "Not enough task balance to execute job, need at least 1000000000040000200000"
-    )]
-    fn test_task_create_deposit_not_enuf_recurring() {
+    #[should_panic(expected = "Task ownership was not offered to you")]
+    fn test_task_transfer_ownership_not_offered() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context.is_view(false).attached_deposit(0).build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(true),
-            Some(U128::from(100000)),
-            Some(200),
-            None,
-        );
+        contract.transfer_task_ownership(task_hash.clone(), Some(accounts(2)));
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.accept_task_ownership(task_hash);
     }
 
-    // NOTE: Useless when agent fee is higher than base gas
-    // #[test]
-    // #[should_panic(expected = "Gas minimum has not been met")]
-    // fn test_task_create_gas_min() {
-    //     let mut context = get_context(accounts(1));
-    //     testing_env!(context.build());
-    //     let mut contract = Contract::new();
-    //     testing_env!(context.is_view(false).attached_deposit(206000000000000000).build());
-    //     contract.create_task(
-    //         accounts(3),
-    //         "increment".to_string(),
-    //         "0 0 */1 * * *".to_string(),
-    //         Some(true),
-    //         Some(U128::from(100000000000000000)),
-    //         Some(0),
-    //         None,
-    //     );
-    // }
-
     #[test]
-    fn test_task_create_slot_schedule() {
+    fn test_task_delegate_pause_resume() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(contract.delegate_storage_fee())
+            .build());
+        contract.update_task_delegates(task_hash.clone(), vec![accounts(4)]);
 
-        // Move forward time and blocks to get more accurate bps
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
-            .block_timestamp(BLOCK_START_TS + (6 * NANO))
-            .block_index(BLOCK_START_BLOCK + 6)
+            .attached_deposit(0)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
             .build());
+        contract.pause_task(task_hash.clone());
+        assert!(contract.get_task(task_hash.clone()).paused);
+        assert_eq!(contract.slots.len(), 0);
+        // Paused tasks are not reported as missing from their slot
+        let (stale, unscheduled) = contract.find_slot_inconsistencies(None, None, None);
+        assert!(stale.is_empty() && unscheduled.is_empty());
 
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/10 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        testing_env!(context.is_view(true).build());
-        let slot = contract
-            .slots
-            .get(&1624151520000000000)
-            .expect("Should have something here");
+        testing_env!(context
+            .is_view(false)
+            .block_timestamp(BLOCK_START_TS + (7200 * NANO))
+            .build());
+        contract.resume_task(task_hash.clone());
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert!(!task.paused);
         assert_eq!(
-            slot[0],
-            [
-                110, 208, 206, 90, 101, 232, 173, 64, 29, 53, 128, 236, 199, 102, 202, 125, 75,
-                162, 235, 254, 94, 145, 121, 8, 160, 199, 94, 146, 137, 128, 165, 81
-            ]
+            contract.slots.get(&task.slot.0).unwrap().to_vec(),
+            vec![task_hash.0.clone()]
         );
-    }
-
-    // TODO: Finish
-    // #[test]
-    // fn test_task_proxy() {
-    //     let mut context = get_context(accounts(1));
-    //     testing_env!(context.build());
-    //     let mut contract = Contract::new();
-    //     testing_env!(context.is_view(false).attached_deposit(6000000000000).build());
-    //     contract.create_task(
-    //         accounts(3),
-    //         "increment".to_string(),
-    //         "*/10 * * * * *".to_string(),
-    //         Some(false),
-    //         None,
-    //         None,
-    //         None,
-    //     );
-    //     testing_env!(context.is_view(false).build());
-    //     contract.register_agent(None);
-
-    //     testing_env!(context.is_view(true).block_index(1260).build());
-    //     assert!(contract.get_all_tasks(None).len() > 0);
-    //     testing_env!(context.is_view(false).build());
-    //     contract.proxy_call();
-    //     assert!(contract.get_all_tasks(None).is_empty());
-    // }
-
-    #[test]
-    // #[should_panic(expected = "Expected 1 promise result.")]
-    #[should_panic(expected = "No task found by hash")]
-    fn test_task_proxy_callback() {
-        let context = get_context(accounts(1));
-        testing_env!(context.build());
-        let mut contract = Contract::new();
-        contract.callback_for_proxy_call(vec![0, 1, 2, 3], U128::from(123400));
+        assert!(task.slot.0 > u128::from(BLOCK_START_TS + (7200 * NANO)));
     }
 
     #[test]
-    #[should_panic(expected = "Agent not registered")]
-    fn test_task_proxy_agent_not_registered() {
+    #[should_panic(expected = "Only owner can withdraw their task balance")]
+    fn test_task_delegate_cannot_withdraw() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(contract.delegate_storage_fee())
             .build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
-        );
+        contract.update_task_delegates(task_hash.clone(), vec![accounts(4)]);
         testing_env!(context
             .is_view(false)
-            .block_index(1260)
-            .attached_deposit(3000000000000300)
-            .prepaid_gas(300000000000)
+            .attached_deposit(1)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
             .build());
-        contract.proxy_call();
+        contract.withdraw_task_deposit(task_hash, None);
     }
 
     #[test]
-    #[should_panic(expected = "Task execution paused")]
-    fn test_task_proxy_paused() {
+    #[should_panic(expected = "Only owner or delegates can manage this task")]
+    fn test_task_pause_not_delegate() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let task_hash = create_withdraw_task(&mut context, &mut contract);
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
             .build());
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
-        );
-        contract.update_settings(None, None, Some(true), None, None, None, None, None, None);
-        testing_env!(context.is_view(false).block_index(1260).build());
-        contract.proxy_call();
+        contract.pause_task(task_hash);
     }
 
     #[test]
-    fn test_task_remove() {
+    fn test_task_low_balance_alert() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        testing_env!(context.is_view(true).build());
-        assert!(contract.get_tasks(None, None, None).is_empty());
+        let cost: u128 = 500000000020000000100;
+        let hook_fee: u128 = 1000000000000000000000;
         testing_env!(context
             .is_view(false)
-            .attached_deposit(ONE_NEAR * 100)
+            .attached_deposit(cost * 4 + hook_fee + storage_fee(&contract))
             .build());
-        let task_hash = contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash.clone(),
+            Some(U128::from(cost * 3 + hook_fee)),
+            Some(BalanceHook {
+                contract_id: accounts(5).to_string(),
+                function_id: "refill_croncat".to_string(),
+            }),
         );
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        let current_slot = contract.get_slot_id(None);
+        let execute = |contract: &mut Contract| {
+            let task = contract.tasks.get(&task_hash.0).unwrap();
+            contract.execute_task(
+                task_hash.0.clone(),
+                task,
+                Some(accounts(4).to_string()),
+                current_slot,
+            );
+            contract.tasks.get(&task_hash.0).unwrap()
+        };
 
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+        // Still at the threshold, no alert
+        let task = execute(&mut contract);
+        assert_eq!(task.total_deposit.0, cost * 3 + hook_fee);
+        assert!(!task.low_balance_notified);
 
-        testing_env!(context.is_view(false).build());
-        contract.remove_task(task_hash);
+        // Under the threshold, the hook is paid by the task & reimbursed to the agent
+        let task = execute(&mut contract);
+        assert_eq!(task.total_deposit.0, cost * 2);
+        assert!(task.low_balance_notified);
+        let agent = contract.get_agent(accounts(4).to_string()).unwrap();
+        assert_eq!(
+            agent.balance.0,
+            AGENT_STORAGE_FEE + (cost - 100) * 2 + hook_fee
+        );
+        assert_eq!(
+            contract.task_balance_total,
+            cost * 2 + storage_fee(&contract)
+        );
 
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 0);
+        // Refilling above the threshold re-arms the alert
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(cost * 2 + hook_fee)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.refill_balance(task_hash.clone());
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert!(!task.low_balance_notified);
     }
 
     #[test]
-    #[should_panic(expected = "Only owner can remove their task.")]
-    fn test_task_remove_not_owner() {
+    #[should_panic(expected = "Hook needs a threshold")]
+    fn test_task_low_balance_hook_no_threshold() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        testing_env!(context.is_view(true).build());
-        assert!(contract.get_tasks(None, None, None).is_empty());
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
             .build());
-        let task_hash = contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash,
             None,
+            Some(BalanceHook {
+                contract_id: accounts(5).to_string(),
+                function_id: "refill_croncat".to_string(),
+            }),
         );
+    }
 
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
-
+    #[test]
+    #[should_panic(expected = "Function ID too long")]
+    fn test_task_low_balance_hook_function_id_too_long() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
         testing_env!(context
             .is_view(false)
-            .signer_account_id(accounts(4))
-            .predecessor_account_id(accounts(4))
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
             .build());
-        contract.remove_task(task_hash);
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash,
+            Some(U128::from(ONE_NEAR)),
+            Some(BalanceHook {
+                contract_id: accounts(5).to_string(),
+                function_id: "a".repeat(MAX_HOOK_FUNCTION_ID_LEN + 1),
+            }),
+        );
     }
 
     #[test]
-    #[should_panic(expected = "No task found by hash")]
-    fn test_task_remove_no_task() {
-        let context = get_context(accounts(1));
+    #[should_panic(expected = "Hook cannot call this contract")]
+    fn test_task_low_balance_hook_self() {
+        let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        contract.remove_task(Base64VecU8::from(vec![0, 1, 2, 3]));
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash,
+            Some(U128::from(ONE_NEAR)),
+            Some(BalanceHook {
+                contract_id: accounts(0).to_string(),
+                function_id: "move_balance".to_string(),
+            }),
+        );
     }
 
     #[test]
-    #[should_panic(expected = "No task found by hash")]
-    fn test_task_refill_no_task() {
-        let context = get_context(accounts(1));
+    #[should_panic(expected = "Target is not allowed")]
+    fn test_task_low_balance_hook_denied() {
+        let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        contract.refill_balance(Base64VecU8::from(vec![0, 1, 2, 3]));
+        contract.update_denied_targets(Some(vec![accounts(5).to_string()]), None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash,
+            Some(U128::from(ONE_NEAR)),
+            Some(BalanceHook {
+                contract_id: accounts(5).to_string(),
+                function_id: "refill_croncat".to_string(),
+            }),
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Only owner can refill their task")]
-    fn test_task_refill_not_owner() {
+    fn test_task_low_balance_hook_denied_later() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
-        testing_env!(context.is_view(true).build());
-        assert!(contract.get_tasks(None, None, None).is_empty());
+        let cost: u128 = 500000000020000000100;
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(cost * 3 + storage_fee(&contract))
             .build());
-        let task_hash = contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
+        contract.update_low_balance_alert(
+            task_hash.clone(),
+            Some(U128::from(cost * 3)),
+            Some(BalanceHook {
+                contract_id: accounts(5).to_string(),
+                function_id: "refill_croncat".to_string(),
+            }),
         );
+        contract.update_denied_targets(Some(vec![accounts(5).to_string()]), None);
+
+        // The alert is still sent, the denied hook is neither called nor paid for
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        let current_slot = contract.get_slot_id(None);
+        contract.execute_task(task_hash.0.clone(), task, None, current_slot);
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert!(task.low_balance_notified);
+        assert_eq!(task.total_deposit.0, cost * 2);
+    }
 
-        testing_env!(context.is_view(true).build());
-        assert_eq!(contract.get_tasks(None, None, None).len(), 1);
+    #[test]
+    fn test_task_execution_gas() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        let mut task = get_sample_task();
+        assert_eq!(
+            contract.get_task_execution_gas(&task),
+            task.gas + GAS_FOR_CALLBACK
+        );
 
-        testing_env!(context
-            .is_view(false)
-            .signer_account_id(accounts(4))
-            .predecessor_account_id(accounts(4))
-            .build());
-        contract.refill_balance(task_hash);
+        // An armed hook may be called during the execution
+        task.recurring = true;
+        task.low_balance_threshold = Some(U128::from(ONE_NEAR));
+        task.low_balance_hook = Some(BalanceHook {
+            contract_id: accounts(5).to_string(),
+            function_id: "refill_croncat".to_string(),
+        });
+        assert_eq!(
+            contract.get_task_execution_gas(&task),
+            task.gas + GAS_FOR_CALLBACK + GAS_FOR_LOW_BALANCE_HOOK
+        );
+        task.low_balance_notified = true;
+        assert_eq!(
+            contract.get_task_execution_gas(&task),
+            task.gas + GAS_FOR_CALLBACK
+        );
     }
 
     #[test]
@@ -1174,22 +4015,19 @@ mod tests {
         let refill_balance: Balance = 1000000000020000000100;
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
             .build());
-        let task_hash = contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "0 0 */1 * * *".to_string(),
-            Some(false),
-            Some(U128::from(100)),
-            Some(200),
-            None,
-        );
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 */1 * * *")
+        });
 
         testing_env!(context.is_view(true).build());
         assert_eq!(contract.get_tasks(None, None, None).len(), 1);
 
-        let available_balance: Balance = contract.available_balance;
+        let task_balance_total: Balance = contract.task_balance_total;
         testing_env!(context
             .is_view(false)
             .signer_account_id(accounts(1))
@@ -1201,17 +4039,135 @@ mod tests {
 
         // Check:
         // - task total_deposit updated
-        // - available_balance updated
+        // - task_balance_total updated
         let updated_task = contract.get_task(task_hash);
         let updated_balance = start_balance.saturating_add(refill_balance);
-        let updated_available_balance = available_balance.saturating_add(refill_balance);
+        let updated_task_balance_total = task_balance_total.saturating_add(refill_balance);
         assert_eq!(
             updated_task.total_deposit.0, updated_balance,
             "Wrong deposit total"
         );
         assert_eq!(
-            contract.available_balance, updated_available_balance,
-            "Wrong total available"
+            contract.task_balance_total, updated_task_balance_total,
+            "Wrong total task balance"
+        );
+    }
+
+    /// Tracked totals must match the stored tasks & agents, and be covered by the account balance
+    fn assert_balance_invariants(contract: &Contract) {
+        let task_balances: Balance = contract
+            .tasks
+            .iter()
+            .map(|(_, t)| t.total_deposit.0 + t.storage_deposit.0)
+            .sum();
+        let agent_balances: Balance = contract
+            .agent_active_queue
+            .iter()
+            .chain(contract.agent_pending_queue.iter())
+            .filter_map(|a| contract.agents.get(&a))
+            .map(|a| a.balance.0)
+            .sum();
+        assert_eq!(contract.task_balance_total, task_balances);
+        assert_eq!(contract.agent_rewards_total, agent_balances);
+
+        let storage_balance = env::storage_byte_cost() * env::storage_usage() as u128;
+        assert!(env::account_balance() >= task_balances + agent_balances + storage_balance);
+    }
+
+    #[test]
+    fn test_balance_invariants() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        assert_balance_invariants(&contract);
+
+        // One active, one pending agent
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.register_agent(None);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(AGENT_STORAGE_FEE)
+            .signer_account_id(accounts(5))
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.register_agent(None);
+        assert_eq!(contract.agent_pending_queue.len(), 1);
+        assert_balance_invariants(&contract);
+
+        // Task execution moves the fee from the task to the agent
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(BLOCK_START_TS + (120 * NANO))
+            .build());
+        contract.proxy_call(None);
+        assert_balance_invariants(&contract);
+        assert_eq!(
+            contract.get_available_balances(),
+            (
+                U128::from(ONE_NEAR - 500000000020000000100 + storage_fee(&contract)),
+                U128::from(AGENT_STORAGE_FEE * 2 + 500000000020000000000)
+            )
+        );
+
+        // Empty slot, base reward
+        contract.proxy_call(None);
+        assert_balance_invariants(&contract);
+
+        // Refill & agent withdrawal
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.refill_balance(task_hash.clone());
+        assert_balance_invariants(&contract);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(0)
+            .signer_account_id(accounts(4))
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.withdraw_task_balance();
+        assert_balance_invariants(&contract);
+
+        // Task removal & agent unregister
+        testing_env!(context
+            .is_view(false)
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.remove_task(task_hash);
+        assert_balance_invariants(&contract);
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1)
+            .signer_account_id(accounts(5))
+            .predecessor_account_id(accounts(5))
+            .build());
+        contract.unregister_agent();
+        assert_balance_invariants(&contract);
+        assert_eq!(
+            contract.get_available_balances(),
+            (U128::from(0), U128::from(AGENT_STORAGE_FEE))
         );
     }
 
@@ -1259,33 +4215,19 @@ mod tests {
         assert_eq!(slot, 1624151460000000000);
 
         testing_env!(context.is_view(false).build());
-        contract.update_settings(
-            None,
-            Some(30_000_000_000),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        );
+        contract.update_settings_with(Settings {
+            slot_granularity: Some(30_000_000_000),
+            ..Default::default()
+        });
         testing_env!(context.is_view(true).build());
         let slot = contract.get_slot_id(None);
         assert_eq!(slot, 1624151490000000000);
 
         testing_env!(context.is_view(false).build());
-        contract.update_settings(
-            None,
-            Some(10_000_000_000),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        );
+        contract.update_settings_with(Settings {
+            slot_granularity: Some(10_000_000_000),
+            ..Default::default()
+        });
         testing_env!(context.is_view(true).build());
         let slot = contract.get_slot_id(None);
         assert_eq!(slot, 1624151500000000000);
@@ -1293,49 +4235,53 @@ mod tests {
 
     #[test]
     fn test_get_slot_from_cadence_ts_check() {
-        let rem = BLOCK_START_TS.clone() % 1_000_000;
-        let secs = ((BLOCK_START_TS.clone() - rem) / 1_000_000_000) + 1;
-        let start_ts = Utc.timestamp(secs as i64, 0).naive_utc().timestamp_nanos() as u64;
+        let rem = BLOCK_START_TS % 1_000_000;
+        let secs = ((BLOCK_START_TS - rem) / 1_000_000_000) + 1;
+        let start_ts = Utc
+            .timestamp_opt(secs as i64, 0)
+            .unwrap()
+            .naive_utc()
+            .timestamp_nanos() as u64;
         let context = get_context(accounts(1));
         testing_env!(context.build());
         let current_block_ts = env::block_timestamp();
 
         // Seconds
-        let schedule1 = Schedule::from_str(&"*/5 * * * * *".to_string()).unwrap();
+        let schedule1 = Schedule::from_str("*/5 * * * * *").unwrap();
         let next_ts1 = schedule1.next_after(&current_block_ts).unwrap();
         println!("TS 1: {} {}", next_ts1, human_readable_time(next_ts1));
         let denom1 = 5 * NANO;
-        let rem1 = start_ts.clone() % denom1;
-        assert_eq!(next_ts1, (start_ts.clone() - rem1) + denom1);
+        let rem1 = start_ts % denom1;
+        assert_eq!(next_ts1, (start_ts - rem1) + denom1);
 
         // Minutes
-        let schedule2 = Schedule::from_str(&"* */5 * * * *".to_string()).unwrap();
+        let schedule2 = Schedule::from_str("* */5 * * * *").unwrap();
         let next_ts2 = schedule2.next_after(&current_block_ts).unwrap();
         println!("TS 2: {} {}", next_ts2, human_readable_time(next_ts2));
         let denom2 = 5 * 60 * NANO;
-        let rem2 = start_ts.clone() % denom2;
-        assert_eq!(next_ts2, (start_ts.clone() - rem2) + denom2);
+        let rem2 = start_ts % denom2;
+        assert_eq!(next_ts2, (start_ts - rem2) + denom2);
 
         // Hours
-        let schedule3 = Schedule::from_str(&"* * */5 * * *".to_string()).unwrap();
+        let schedule3 = Schedule::from_str("* * */5 * * *").unwrap();
         let next_ts3 = schedule3.next_after(&current_block_ts).unwrap();
         println!("TS 3: {} {}", next_ts3, human_readable_time(next_ts3));
         assert_eq!(next_ts3, 1624165200000000000);
 
         // Days
-        let schedule4 = Schedule::from_str(&"* * * 10 * *".to_string()).unwrap();
+        let schedule4 = Schedule::from_str("* * * 10 * *").unwrap();
         let next_ts4 = schedule4.next_after(&current_block_ts).unwrap();
         println!("TS 4: {} {}", next_ts4, human_readable_time(next_ts4));
         assert_eq!(next_ts4, 1625875200000000000);
 
         // Month
-        let schedule5 = Schedule::from_str(&"* * * * 10 *".to_string()).unwrap();
+        let schedule5 = Schedule::from_str("* * * * 10 *").unwrap();
         let next_ts5 = schedule5.next_after(&current_block_ts).unwrap();
         println!("TS 5: {} {}", next_ts5, human_readable_time(next_ts5));
         assert_eq!(next_ts5, 1633046400000000000);
 
         // Year
-        let schedule6 = Schedule::from_str(&"* * * * * * 2025".to_string()).unwrap();
+        let schedule6 = Schedule::from_str("* * * * * * 2025").unwrap();
         let next_ts6 = schedule6.next_after(&current_block_ts).unwrap();
         println!("TS 6: {} {}", next_ts6, human_readable_time(next_ts6));
         assert_eq!(next_ts6, 1750381904000000000);
@@ -1348,30 +4294,85 @@ mod tests {
         let contract = Contract::new();
         testing_env!(context
             .is_view(false)
-            .block_index(BLOCK_START_BLOCK.clone() + 1)
-            .block_timestamp(BLOCK_START_TS.clone() + 1_000_000_000)
+            .block_index(BLOCK_START_BLOCK + 1)
+            .block_timestamp(BLOCK_START_TS + 1_000_000_000)
             .build());
         testing_env!(context.is_view(true).build());
-        let slot1 = contract.get_slot_from_cadence("*/5 * * * * *".to_string()); // Immediately next slot (since every 5 seconds)
+        let slot1 = contract.get_slot_from_cadence("*/5 * * * * *".to_string(), None); // Immediately next slot (since every 5 seconds)
         println!("SLOT 1 {}", slot1);
         assert_eq!(slot1, 1624151520000000000);
-        let slot2 = contract.get_slot_from_cadence("* */5 * * * *".to_string()); // Every 5 mins
+        let slot2 = contract.get_slot_from_cadence("* */5 * * * *".to_string(), None); // Every 5 mins
         println!("SLOT 2 {}", slot2);
         assert_eq!(slot2, 1624151760000000000);
-        let slot3 = contract.get_slot_from_cadence("* * */5 * * *".to_string()); // Every 5th hour
+        let slot3 = contract.get_slot_from_cadence("* * */5 * * *".to_string(), None); // Every 5th hour
         println!("SLOT 3 {}", slot3);
         assert_eq!(slot3, 1624165260000000000);
-        let slot4 = contract.get_slot_from_cadence("* * * 10 * *".to_string()); // The 10th day of Month
+        let slot4 = contract.get_slot_from_cadence("* * * 10 * *".to_string(), None); // The 10th day of Month
         println!("SLOT 4 {}", slot4);
         assert_eq!(slot4, 1625875260000000000);
-        let slot5 = contract.get_slot_from_cadence("* * * * 10 *".to_string()); // The 10th Month of the Year
+        let slot5 = contract.get_slot_from_cadence("* * * * 10 *".to_string(), None); // The 10th Month of the Year
         println!("SLOT 5 {}", slot5);
         assert_eq!(slot5, 1633046460000000000);
-        let slot6 = contract.get_slot_from_cadence("* * * * * * 2025".to_string());
+        let slot6 = contract.get_slot_from_cadence("* * * * * * 2025".to_string(), None);
         println!("SLOT 6 {}", slot6);
         assert_eq!(slot6, 1750381920000000000);
     }
 
+    #[test]
+    fn test_get_slot_from_cadence_timezone() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        let cadence = "0 0 9 * * *".to_string(); // Every day at 09:00
+        let utc_slot = contract.get_slot_from_cadence(cadence.clone(), None);
+        assert_eq!(utc_slot, 1624179660000000000);
+        // 09:00 EDT is 13:00 UTC
+        let ny_slot =
+            contract.get_slot_from_cadence(cadence.clone(), Some("America/New_York".to_string()));
+        assert_eq!(ny_slot, 1624194060000000000);
+
+        // 09:00 CET in winter is 08:00 UTC
+        let schedule = Schedule::from_str(&cadence).unwrap();
+        let berlin = Some("Europe/Berlin".to_string());
+        let winter_slot = contract.get_slot_from_schedule(&schedule, &berlin, 1610236800000000000);
+        assert_eq!(winter_slot, 1610265660000000000);
+
+        // 02:30 does not exist on the day DST starts, so the next day is used
+        let schedule = Schedule::from_str("0 30 2 * * *").unwrap();
+        let dst_slot = contract.get_slot_from_schedule(&schedule, &berlin, 1616846400000000000);
+        assert_eq!(dst_slot, 1616977860000000000);
+    }
+
+    #[test]
+    fn test_validate_cadence_timezone() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        let cadence = "0 0 9 * * 1-5".to_string();
+        assert!(contract.validate_cadence(cadence.clone(), None));
+        assert!(contract.validate_cadence(cadence.clone(), Some("Asia/Tokyo".to_string())));
+        assert!(!contract.validate_cadence(cadence.clone(), Some("Mars/Olympus".to_string())));
+        assert!(!contract.validate_cadence("0 0 25 * * *".to_string(), None));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cadence string invalid")]
+    fn test_task_create_bad_timezone() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(ONE_NEAR + storage_fee(&contract))
+            .build());
+        contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            timezone: Some("Europe/Atlantis".to_string()),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 9 * * *")
+        });
+    }
+
     #[test]
     fn test_hash_compute() {
         let context = get_context(accounts(3));
@@ -0,0 +1,150 @@
+use crate::*;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::MockedBlockchain;
+
+/// Storage paid on top of the balance of a task without arguments
+pub(crate) fn storage_fee(contract: &Contract) -> u128 {
+    contract.task_storage_fee(&Base64VecU8::from(vec![]))
+}
+
+/// Mocked receipt holding a single transfer, other receipts do not parse
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct TransferReceipt {
+    receiver_id: AccountId,
+    actions: Vec<TransferAction>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+enum TransferAction {
+    Transfer { deposit: Balance },
+}
+
+/// Transfers created so far, as (receiver, amount)
+pub(crate) fn get_transfers() -> Vec<(AccountId, Balance)> {
+    near_sdk::test_utils::get_created_receipts()
+        .iter()
+        .filter_map(|receipt| {
+            let json = near_sdk::serde_json::to_string(receipt).ok()?;
+            near_sdk::serde_json::from_str::<TransferReceipt>(&json).ok()
+        })
+        .flat_map(|receipt| {
+            let receiver_id = receipt.receiver_id;
+            receipt
+                .actions
+                .into_iter()
+                .map(move |TransferAction::Transfer { deposit }| (receiver_id.clone(), deposit))
+        })
+        .collect()
+}
+
+/// Sets the result of the promise a callback is reading, keeping the contract storage
+pub(crate) fn set_promise_result(context: &VMContextBuilder, result: PromiseResult) {
+    let storage = env::take_blockchain_interface()
+        .unwrap()
+        .as_mut_mocked_blockchain()
+        .unwrap()
+        .take_storage();
+    env::set_blockchain_interface(Box::new(MockedBlockchain::new(
+        context.context.clone(),
+        Default::default(),
+        Default::default(),
+        vec![result],
+        storage,
+        Default::default(),
+        None,
+    )));
+}
+
+/// Arguments of `create_task`, the optional ones default to None
+pub(crate) struct TaskArgs {
+    pub contract_id: ValidAccountId,
+    pub function_id: String,
+    pub cadence: String,
+    pub recurring: Option<bool>,
+    pub deposit: Option<U128>,
+    pub gas: Option<Gas>,
+    pub arguments: Option<Base64VecU8>,
+    pub priority_tip: Option<U128>,
+    pub catch_up: Option<CatchUpPolicy>,
+    pub max_delay: Option<U64>,
+    pub timezone: Option<String>,
+    pub gas_estimate: Option<Gas>,
+}
+
+impl TaskArgs {
+    pub(crate) fn new(contract_id: ValidAccountId, function_id: &str, cadence: &str) -> Self {
+        TaskArgs {
+            contract_id,
+            function_id: function_id.to_string(),
+            cadence: cadence.to_string(),
+            recurring: None,
+            deposit: None,
+            gas: None,
+            arguments: None,
+            priority_tip: None,
+            catch_up: None,
+            max_delay: None,
+            timezone: None,
+            gas_estimate: None,
+        }
+    }
+}
+
+/// Arguments of `update_settings`, all default to None
+#[derive(Default)]
+pub(crate) struct Settings {
+    pub owner_id: Option<AccountId>,
+    pub slot_granularity: Option<u64>,
+    pub paused: Option<bool>,
+    pub agent_fee: Option<U128>,
+    pub gas_price: Option<U128>,
+    pub proxy_callback_gas: Option<U64>,
+    pub agent_task_ratio: Option<Vec<U64>>,
+    pub agents_eject_threshold: Option<U128>,
+    pub treasury_id: Option<AccountId>,
+    pub staking_pool_id: Option<AccountId>,
+    pub gas_price_floor: Option<U128>,
+    pub gas_price_ceiling: Option<U128>,
+    pub max_tasks_per_owner: Option<U64>,
+    pub max_tasks_per_slot: Option<U64>,
+}
+
+impl Contract {
+    pub(crate) fn create_task_with(&mut self, args: TaskArgs) -> Base64VecU8 {
+        self.create_task(
+            args.contract_id,
+            args.function_id,
+            args.cadence,
+            args.recurring,
+            args.deposit,
+            args.gas,
+            args.arguments,
+            args.priority_tip,
+            args.catch_up,
+            args.max_delay,
+            args.timezone,
+            args.gas_estimate,
+        )
+    }
+
+    pub(crate) fn update_settings_with(&mut self, settings: Settings) {
+        self.update_settings(
+            settings.owner_id,
+            settings.slot_granularity,
+            settings.paused,
+            settings.agent_fee,
+            settings.gas_price,
+            settings.proxy_callback_gas,
+            settings.agent_task_ratio,
+            settings.agents_eject_threshold,
+            settings.treasury_id,
+            settings.staking_pool_id,
+            settings.gas_price_floor,
+            settings.gas_price_ceiling,
+            settings.max_tasks_per_owner,
+            settings.max_tasks_per_slot,
+        )
+    }
+}
@@ -1,8 +1,9 @@
+use crate::tasks::GAS_FOR_PROXY_CALL;
 use crate::*;
 use near_sdk::serde_json;
 
 pub const NO_DEPOSIT: Balance = 0;
-pub const VIEW_CALL_GAS: Gas = 240_000_000_000_000;
+pub const VIEW_CALL_GAS: Gas = 25_000_000_000_000; // plenty for a view answering a boolean
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
@@ -55,7 +56,7 @@ impl Contract {
         arguments: Option<Base64VecU8>,
     ) -> Base64VecU8 {
         // No adding triggers while contract is paused
-        assert_eq!(self.paused, false, "Create trigger paused");
+        assert!(!self.paused, "Create trigger paused");
         // Check attached deposit includes trigger_storage_usage
         assert!(
             env::attached_deposit() >= self.trigger_storage_usage as u128,
@@ -63,14 +64,18 @@ impl Contract {
             self.trigger_storage_usage
         );
         // prevent dumb mistakes
-        assert!(contract_id.to_string().len() > 0, "Contract ID missing");
-        assert!(function_id.len() > 0, "Function ID missing");
-        assert!(task_hash.0.len() > 0, "Task Hash missing");
+        assert!(!contract_id.to_string().is_empty(), "Contract ID missing");
+        assert!(!function_id.is_empty(), "Function ID missing");
+        assert!(!task_hash.0.is_empty(), "Task Hash missing");
         assert_ne!(
             contract_id.clone().to_string(),
             env::current_account_id(),
             "Trigger cannot call self"
         );
+        assert!(
+            self.is_target_allowed(&contract_id.to_string(), &function_id),
+            "Target is not allowed"
+        );
 
         // Confirm owner of task is same
         let task = self.tasks.get(&task_hash.0).expect("No task found");
@@ -115,12 +120,7 @@ impl Contract {
         );
 
         // If owner, allow to remove task
-        self.triggers
-            .remove(&hash)
-            .expect("No trigger found by hash");
-
-        // Refund trigger storage
-        Promise::new(trigger.owner_id).transfer(self.trigger_storage_usage as u128);
+        self.drop_trigger(&hash, trigger);
     }
 
     /// Get the hash of a trigger based on parameters
@@ -207,7 +207,7 @@ impl Contract {
     /// ```
     pub fn proxy_conditional_call(&mut self, trigger_hash: Base64VecU8) {
         // No adding tasks while contract is paused
-        assert_eq!(self.paused, false, "Task execution paused");
+        assert!(!self.paused, "Task execution paused");
 
         // only registered agent signed, because micropayments will benefit long term
         let agent_opt = self.agents.get(&env::predecessor_account_id());
@@ -216,12 +216,8 @@ impl Contract {
         }
 
         // TODO: Think about agent rewards - as they could pay for a failed CB
-        let trigger = self
-            .triggers
-            .get(&trigger_hash.into())
-            .expect("No trigger found by hash");
-
-        // TODO: check the task actually exists
+        let hash: Vec<u8> = trigger_hash.into();
+        let trigger = self.triggers.get(&hash).expect("No trigger found by hash");
 
         // Make sure this isnt calling manager
         assert_ne!(
@@ -230,10 +226,40 @@ impl Contract {
             "Trigger cannot call self"
         );
 
+        let task = self
+            .tasks
+            .get(&trigger.task_hash.0)
+            .expect("No task found by hash");
+
+        // Triggers of a previous task owner are no longer authorized to spend the task balance
+        if trigger.owner_id != task.owner_id {
+            log!("Trigger owner no longer owns the task, removing trigger");
+            self.drop_trigger(&hash, trigger);
+            return;
+        }
+
+        // Trigger targets denied after the trigger was created are cut off, refunding its storage
+        if !self.is_target_allowed(&trigger.contract_id, &trigger.function_id) {
+            log!("Trigger target is not allowed, removing trigger");
+            self.drop_trigger(&hash, trigger);
+            return;
+        }
+
+        // Callback needs enough gas to execute the task & schedule its next slot
+        let callback_gas = GAS_FOR_CALLBACK.saturating_add(self.get_task_execution_gas(&task));
+        let required_gas = VIEW_CALL_GAS
+            .saturating_add(callback_gas)
+            .saturating_add(GAS_FOR_PROXY_CALL);
+        assert!(
+            env::prepaid_gas() >= required_gas,
+            "Not enough gas attached for the trigger view & task execution, {} required",
+            required_gas
+        );
+
         // Call external contract with task variables
         let promise_first = env::promise_create(
             trigger.contract_id.clone(),
-            &trigger.function_id.as_bytes(),
+            trigger.function_id.as_bytes(),
             trigger.arguments.0.as_slice(),
             NO_DEPOSIT,
             VIEW_CALL_GAS,
@@ -249,13 +275,24 @@ impl Contract {
             .to_string()
             .as_bytes(),
             NO_DEPOSIT,
-            GAS_FOR_CALLBACK,
+            callback_gas,
         );
         env::promise_return(promise_second);
     }
 
+    /// Removes a trigger from the catalog & refunds its storage to the trigger owner
+    fn drop_trigger(&mut self, hash: &Vec<u8>, trigger: Trigger) {
+        self.triggers
+            .remove(hash)
+            .expect("No trigger found by hash");
+
+        // Refund trigger storage
+        Promise::new(trigger.owner_id).transfer(self.trigger_storage_usage as u128);
+    }
+
     /// !IMPORTANT!:: BETA FEATURE!!!!!!!!!
     /// Callback, if response is TRUE, then do the actual proxy call
+    /// Execution shares the same accounting, exit & rescheduling logic as proxy_call
     #[private]
     pub fn proxy_conditional_callback(&mut self, task_hash: Base64VecU8, agent_id: AccountId) {
         assert_eq!(
@@ -263,7 +300,7 @@ impl Contract {
             1,
             "Expected 1 promise result."
         );
-        let mut agent = self.agents.get(&agent_id).expect("Agent not found");
+        let agent = self.agents.get(&agent_id).expect("Agent not found");
         match env::promise_result(0) {
             PromiseResult::NotReady => {
                 unreachable!()
@@ -272,58 +309,23 @@ impl Contract {
                 let result: CroncatTriggerResponse = serde_json::de::from_slice(&trigger_result)
                     .expect("Could not get result from trigger");
 
-                // TODO: Refactor to re-used method
                 if result.0 {
-                    let mut task = self
-                        .tasks
-                        .get(&task_hash.clone().into())
-                        .expect("No task found by hash");
-
-                    // Fee breakdown:
-                    // - Used Gas: Task Txn Fee Cost
-                    // - Agent Fee: Incentivize Execution SLA
-                    //
-                    // Task Fee Examples:
-                    // Total Fee = Gas Fee + Agent Fee
-                    // Total Balance = Task Deposit + Total Fee
-                    //
-                    // NOTE: Gas cost includes the cross-contract call & internal logic of this contract.
-                    // Direct contract gas fee will be lower than task execution costs, however
-                    // we require the task owner to appropriately estimate gas for overpayment.
-                    // The gas overpayment will also accrue to the agent since there is no way to read
-                    // how much gas was actually used on callback.
-                    let call_fee_used = u128::from(task.gas) * self.gas_price;
-                    let call_total_fee = call_fee_used + self.agent_fee;
-                    let call_total_balance = task.deposit.0 + call_total_fee;
-
-                    // Update agent storage
-                    // Increment agent reward & task count
-                    // Reward for agent MUST include the amount of gas used as a reimbursement
-                    agent.balance = U128::from(agent.balance.0 + call_total_fee);
-                    agent.total_tasks_executed = U128::from(agent.total_tasks_executed.0 + 1);
-                    self.available_balance = self.available_balance - call_total_fee;
-
-                    // Reset missed slot, if any
-                    if agent.last_missed_slot != 0 {
-                        agent.last_missed_slot = 0;
+                    let hash: Vec<u8> = task_hash.into();
+                    let task = self.tasks.get(&hash).expect("No task found by hash");
+                    if task.paused {
+                        log!("Task paused");
+                        // reward agent for diligence, the trigger view was still paid
+                        self.send_base_agent_reward(agent_id, agent, false);
+                        return;
+                    }
+
+                    // TODO: support CroncatTriggerResponse optional view arguments
+                    let current_slot = self.get_slot_id(None);
+                    if let Some(promise) =
+                        self.execute_task(hash, task, Some(agent_id), current_slot)
+                    {
+                        env::promise_return(promise);
                     }
-                    self.agents.insert(&env::signer_account_id(), &agent);
-
-                    // Decrease task balance, Update task storage
-                    task.total_deposit = U128::from(task.total_deposit.0 - call_total_balance);
-                    self.tasks.insert(&task_hash.into(), &task);
-
-                    // Call external contract with task variables
-                    let promise_first = env::promise_create(
-                        task.contract_id.clone(),
-                        &task.function_id.as_bytes(),
-                        // TODO: support CroncatTriggerResponse optional view arguments
-                        task.arguments.0.as_slice(),
-                        task.deposit.0,
-                        task.gas,
-                    );
-
-                    env::promise_return(promise_first);
                 } else {
                     log!("Trigger returned false");
                 }
@@ -331,7 +333,7 @@ impl Contract {
             PromiseResult::Failed => {
                 // Problem with the creation transaction, reward money has been returned to this contract.
                 log!("Trigger call failed");
-                self.send_base_agent_reward(agent);
+                self.send_base_agent_reward(agent_id, agent, true);
             }
         }
     }
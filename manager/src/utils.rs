@@ -9,10 +9,9 @@ impl Contract {
     ///
     /// near call manager_v1.croncat.testnet tick '{}'
     pub fn tick(&mut self) {
-        // TBD: Internal staking management
         log!(
             "Balances [Operations, Treasury]:  [{},{}]",
-            self.available_balance,
+            self.available_balance(),
             self.staked_balance
         );
 
@@ -27,22 +26,17 @@ impl Contract {
 
         // Loop all agents to assess if really active
         // Why the copy here? had to get a mutable reference from immutable self instance
-        let mut bad_agents: Vec<AccountId> = Vec::from(self.agent_active_queue.to_vec());
+        let mut bad_agents: Vec<AccountId> = self.agent_active_queue.to_vec();
         bad_agents.retain(|agent_id| {
-            let _agent = self.agents.get(&agent_id);
+            let _agent = self.agents.get(agent_id);
 
             if let Some(_agent) = _agent {
-                let last_slot = u128::from(_agent.last_missed_slot);
+                let last_slot = _agent.last_missed_slot;
 
                 // Check if any agents need to be ejected, looking at previous task slot and current
                 // LOGIC: If agent misses X number of slots, eject!
-                if current_slot
+                current_slot
                     > last_slot + (self.agents_eject_threshold * u128::from(self.slot_granularity))
-                {
-                    true
-                } else {
-                    false
-                }
             } else {
                 false
             }
@@ -74,9 +68,9 @@ impl Contract {
         // Check if there are more tasks to allow a new agent
         if total_available_agents > total_agents {
             // There's enough tasks to support another agent, check if we have any pending
-            if self.agent_pending_queue.len() > 0 {
+            if !self.agent_pending_queue.is_empty() {
                 // FIFO grab pending agents
-                let agent_id = self.agent_pending_queue.swap_remove(0);
+                let agent_id = self.swap_remove_queued_agent(false, 0);
                 if let Some(mut agent) = self.agents.get(&agent_id) {
                     agent.status = agent::AgentStatus::Active;
                     self.agents.insert(&agent_id, &agent);
@@ -1,4 +1,62 @@
 use crate::*;
+use std::ops::Bound;
+
+pub const PREVIEW_CADENCE_MAX: u64 = 100;
+pub const ESTIMATE_MAX_SCAN: u64 = 200; // occurrences walked before extrapolating
+pub const SLOTS_RANGE_MAX: u64 = 100;
+pub const CALLBACK_MAX_DELAY: u64 = 60_000_000_000; // nanos an execution callback can take to reschedule its task
+
+/// Stale (slot, task hash) entries & unscheduled task hashes, see `find_slot_inconsistencies`
+type SlotInconsistencies = (Vec<(u128, Vec<u8>)>, Vec<Vec<u8>>);
+
+/// Upcoming occurrences of a cadence, or why it could not be parsed
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CadencePreview {
+    /// Occurrence timestamps (NANOS)
+    pub timestamps: Vec<U64>,
+
+    /// Slot each occurrence would be scheduled in
+    pub slots: Vec<U128>,
+
+    pub error: Option<String>,
+}
+
+/// Tasks scheduled in a slot, see `get_slots_range`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SlotSummary {
+    /// Slot id, the timestamp (NANOS) the slot or sub-slot starts at
+    pub slot: U128,
+
+    pub task_count: U64,
+
+    /// Task hashes, in execution order
+    pub task_hashes: Vec<Base64VecU8>,
+}
+
+/// Projected cost of a task, see `estimate_task_cost`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TaskCostEstimate {
+    /// Balance used by each execution: deposit, gas, agent fee & priority tip
+    pub cost_per_execution: U128,
+
+    /// Minimum balance to attach when creating the task, storage included
+    pub minimum_deposit: U128,
+
+    /// Executions requested, or counted within the requested duration
+    pub executions: Option<U64>,
+
+    /// Balance to attach to fund the requested executions, storage included
+    pub required_deposit: Option<U128>,
+
+    /// Executions the given total deposit pays for, after storage
+    pub executions_funded: Option<U64>,
+
+    /// Projected timestamp (NANOS) of the last execution the total deposit pays for
+    pub depletion_ts: Option<U64>,
+}
 
 #[near_bindgen]
 impl Contract {
@@ -16,6 +74,7 @@ impl Contract {
     /// ```bash
     /// near view manager_v1.croncat.testnet get_info
     /// ```
+    #[allow(clippy::type_complexity)]
     pub fn get_info(
         &self,
     ) -> (
@@ -46,7 +105,7 @@ impl Contract {
             U128::from(self.agents_eject_threshold),
             U64::from(self.slots.len()),
             U64::from(self.tasks.len()),
-            U128::from(self.available_balance),
+            U128::from(self.available_balance()),
             U128::from(self.staked_balance),
             U128::from(self.agent_fee),
             U128::from(self.gas_price),
@@ -73,33 +132,267 @@ impl Contract {
         let base_balance = BASE_BALANCE; // safety overhead
         let storage_balance = env::storage_byte_cost().saturating_mul(env::storage_usage() as u128);
         let required_balance = base_balance.saturating_add(storage_balance);
-        let total_balance: u128 = if self.available_balance > 0 {
-            self.available_balance
-        } else {
-            env::account_balance()
-        };
-        let surplus = u128::max(total_balance.saturating_sub(required_balance), 0);
+        let surplus = env::account_balance()
+            .saturating_sub(self.available_balance())
+            .saturating_sub(required_balance);
 
         // Return surplus value in case we want to trigger staking based off outcome
         (
             U128::from(env::account_balance()),
-            U128::from(self.available_balance),
+            U128::from(self.available_balance()),
             U128::from(self.staked_balance),
             U128::from(surplus),
         )
     }
 
+    /// Gets the progress of a paged `calc_balances_page` run, if one is in progress
+    /// Returns (tasks summed, total tasks, agents summed, total active & pending agents)
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_balances_progress
+    /// ```
+    pub fn get_balances_progress(&self) -> Option<(U64, U64, U64, U64)> {
+        self.balance_reconciliation.as_ref().map(|progress| {
+            (
+                progress.task_index,
+                U64::from(self.tasks.len()),
+                U64::from(progress.active_agent_index.0 + progress.pending_agent_index.0),
+                U64::from(self.agent_active_queue.len() + self.agent_pending_queue.len()),
+            )
+        })
+    }
+
+    /// Gets the balances held on behalf of users, tracked on every change
+    /// Returns (total task balances, total agent balances)
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_available_balances
+    /// ```
+    pub fn get_available_balances(&self) -> (U128, U128) {
+        (
+            U128::from(self.task_balance_total),
+            U128::from(self.agent_rewards_total),
+        )
+    }
+
+    /// Gets the gas price charged per execution, with its bounds & the agent reported samples
+    /// Returns (gas price, floor, ceiling, samples)
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_gas_price_info
+    /// ```
+    pub fn get_gas_price_info(&self) -> (U128, U128, U128, Vec<U128>) {
+        (
+            U128::from(self.gas_price),
+            U128::from(self.gas_price_floor),
+            U128::from(self.gas_price_ceiling),
+            self.gas_price_samples
+                .iter()
+                .map(|(_, s)| U128::from(*s))
+                .collect(),
+        )
+    }
+
+    /// Gets the storage fee to attach on top of the task balance when creating a task.
+    /// It is refunded to the task owner once the task exits.
+    /// Returns (storage fee, max tasks per owner, max tasks per slot)
+    ///
+    /// Optional Parameters:
+    /// "arguments" - Arguments of the task, each byte is charged as storage.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_task_storage_fee '{"arguments": ""}'
+    /// ```
+    pub fn get_task_storage_fee(&self, arguments: Option<Base64VecU8>) -> (U128, U64, U64) {
+        let arguments = arguments.unwrap_or_else(|| Base64VecU8::from(vec![]));
+        (
+            U128::from(self.task_storage_fee(&arguments)),
+            U64::from(self.max_tasks_per_owner),
+            U64::from(self.max_tasks_per_slot),
+        )
+    }
+
     /// Check if a cadence string is valid by attempting to parse it
     ///
+    /// Optional Parameters:
+    /// "timezone" - IANA timezone the cadence will be evaluated in, must be a known zone.
+    ///
     /// ```bash
-    /// near view manager_v1.croncat.testnet validate_cadence '{"cadence": "0 0 * * * *"}'
+    /// near view manager_v1.croncat.testnet validate_cadence '{"cadence": "0 0 * * * *", "timezone": "Europe/Berlin"}'
     /// ```
-    pub fn validate_cadence(&self, cadence: String) -> bool {
+    pub fn validate_cadence(&self, cadence: String, timezone: Option<String>) -> bool {
         let s = Schedule::from_str(&cadence);
-        if s.is_ok() {
-            true
+        if s.is_err() {
+            return false;
+        }
+        if let Some(timezone) = timezone {
+            timezone.parse::<Tz>().is_ok()
         } else {
-            false
+            true
+        }
+    }
+
+    /// Previews the next occurrences of a cadence, with the slots tasks would be scheduled in.
+    /// Each slot is computed as if the previous occurrence was the current block.
+    ///
+    /// NOTE: The preview assumes every execution happens on time. Tasks are rescheduled from
+    /// the block their callback lands in, so late executions shift the following slots.
+    ///
+    /// Optional Parameters:
+    /// "count" - Number of occurrences to return, default 10, max 100.
+    /// "from_ts" - Timestamp (NANOS) to start from, defaults to the current block.
+    /// "timezone" - IANA timezone the cadence is evaluated in.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet preview_cadence '{"cadence": "0 0 9 * * 1-5", "count": 5, "timezone": "Europe/Berlin"}'
+    /// ```
+    pub fn preview_cadence(
+        &self,
+        cadence: String,
+        count: Option<u64>,
+        from_ts: Option<U64>,
+        timezone: Option<String>,
+    ) -> CadencePreview {
+        let mut preview = CadencePreview {
+            timestamps: Vec::new(),
+            slots: Vec::new(),
+            error: None,
+        };
+        let schedule = match Schedule::from_str(&cadence) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                preview.error = Some(format!("Cadence string invalid: {}", e));
+                return preview;
+            }
+        };
+        if let Some(tz) = timezone.clone() {
+            if tz.parse::<Tz>().is_err() {
+                preview.error = Some(format!("Timezone invalid: {}", tz));
+                return preview;
+            }
+        }
+
+        let count = u64::min(count.unwrap_or(10), PREVIEW_CADENCE_MAX);
+        let mut ts = from_ts.map(|t| t.0).unwrap_or_else(env::block_timestamp);
+        for _ in 0..count {
+            let next_ts = match self.get_next_ts(&schedule, &timezone, ts) {
+                Some(next_ts) => next_ts,
+                None => break,
+            };
+            preview.slots.push(U128::from(
+                self.get_slot_from_schedule(&schedule, &timezone, ts),
+            ));
+            preview.timestamps.push(U64::from(next_ts));
+            ts = next_ts;
+        }
+
+        preview
+    }
+
+    /// Estimates the cost of creating & sustaining a task, at the current gas price & agent fee.
+    /// Occurrences are projected from the current block, extrapolating past ESTIMATE_MAX_SCAN.
+    ///
+    /// Optional Parameters:
+    /// "recurring", "deposit", "gas", "arguments", "priority_tip", "timezone" - Same as `create_task`.
+    /// "executions" - Number of executions to fund.
+    /// "duration" - Nanoseconds to fund executions for, used when "executions" is not set.
+    /// "total_deposit" - Balance to attach, to get the executions it funds & when it runs out.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet estimate_task_cost '{"cadence": "0 0 * * * *", "recurring": true, "gas": 2400000000000, "duration": "2592000000000000", "total_deposit": "1000000000000000000000000"}'
+    /// ```
+    pub fn estimate_task_cost(
+        &self,
+        cadence: String,
+        recurring: Option<bool>,
+        deposit: Option<U128>,
+        gas: Option<Gas>,
+        arguments: Option<Base64VecU8>,
+        priority_tip: Option<U128>,
+        timezone: Option<String>,
+        executions: Option<U64>,
+        duration: Option<U64>,
+        total_deposit: Option<U128>,
+    ) -> TaskCostEstimate {
+        assert!(
+            self.validate_cadence(cadence.clone(), timezone.clone()),
+            "Cadence string invalid"
+        );
+        let recurring = recurring.unwrap_or(false);
+        let cost = self.get_execution_cost(
+            deposit.map(|d| d.0).unwrap_or(0),
+            gas.unwrap_or(GAS_BASE_FEE),
+            priority_tip.map(|t| t.0).unwrap_or(0),
+        );
+        // Same minimum as `create_task`, storage is paid on top
+        let storage_fee =
+            self.task_storage_fee(&arguments.unwrap_or_else(|| Base64VecU8::from(vec![])));
+        let minimum_balance = if recurring { cost * 2 } else { cost };
+        let minimum_deposit = minimum_balance.saturating_add(storage_fee);
+
+        // Collect upcoming occurrences, enough to extrapolate the rest
+        let schedule = Schedule::from_str(&cadence).unwrap();
+        let now = env::block_timestamp();
+        let max_occurrences = if recurring { ESTIMATE_MAX_SCAN } else { 1 };
+        let mut occurrences: Vec<u64> = Vec::new();
+        while (occurrences.len() as u64) < max_occurrences {
+            let from_ts = *occurrences.last().unwrap_or(&now);
+            match self.get_next_ts(&schedule, &timezone, from_ts) {
+                Some(ts) => occurrences.push(ts),
+                None => break,
+            }
+        }
+        // Only a full scan continues past its last occurrence
+        let projected = |count: u64| -> Option<u64> {
+            let last = *occurrences.last()?;
+            if count <= occurrences.len() as u64 {
+                Some(occurrences[count as usize - 1])
+            } else if recurring && occurrences.len() as u64 == ESTIMATE_MAX_SCAN {
+                let span =
+                    u128::from(last - now) * u128::from(count) / u128::from(ESTIMATE_MAX_SCAN);
+                Some(now.saturating_add(span as u64))
+            } else {
+                None
+            }
+        };
+
+        let executions = executions.map(|e| e.0).or_else(|| {
+            let end = now.saturating_add(duration?.0);
+            let counted = occurrences.iter().filter(|ts| **ts <= end).count() as u64;
+            let last = *occurrences.last().unwrap_or(&now);
+            if recurring && counted == ESTIMATE_MAX_SCAN && last > now {
+                let extrapolated =
+                    u128::from(end - now) * u128::from(ESTIMATE_MAX_SCAN) / u128::from(last - now);
+                Some(extrapolated as u64)
+            } else {
+                Some(counted)
+            }
+        });
+        let required_deposit = executions.map(|e| {
+            u128::max(cost.saturating_mul(u128::from(e)), minimum_balance)
+                .saturating_add(storage_fee)
+        });
+
+        let executions_funded = total_deposit.map(|d| {
+            let balance = d.0.saturating_sub(storage_fee);
+            let funded = balance.checked_div(cost).unwrap_or(0);
+            if recurring {
+                u128::min(funded, u128::from(u64::MAX)) as u64
+            } else {
+                u128::min(funded, 1) as u64
+            }
+        });
+        let depletion_ts = executions_funded
+            .filter(|funded| *funded > 0)
+            .and_then(projected);
+
+        TaskCostEstimate {
+            cost_per_execution: U128::from(cost),
+            minimum_deposit: U128::from(minimum_deposit),
+            executions: executions.map(U64::from),
+            required_deposit: required_deposit.map(U128::from),
+            executions_funded: executions_funded.map(U64::from),
+            depletion_ts: depletion_ts.map(U64::from),
         }
     }
 
@@ -118,15 +411,9 @@ impl Contract {
 
         // Get tasks based on current slot.
         // (Or closest past slot if there are leftovers.)
-        let slot_ballpark = self.slots.floor_key(&current_slot);
-        if let Some(k) = slot_ballpark {
-            let ret: Vec<Base64VecU8> = self
-                .slots
-                .get(&k)
-                .unwrap()
-                .into_iter()
-                .map(Base64VecU8::from)
-                .collect();
+        let slot_ballpark = self.get_slot_ballpark(current_slot);
+        if let Some(slot_tasks) = self.slots.get(&slot_ballpark) {
+            let ret: Vec<Base64VecU8> = slot_tasks.iter().map(Base64VecU8::from).collect();
 
             (ret, U128::from(current_slot))
         } else {
@@ -134,16 +421,55 @@ impl Contract {
         }
     }
 
-    /// Gets list of active slot ids
+    /// Gets list of active slot ids, including the sub-slots full slots spilled into
     ///
     /// ```bash
     /// near view manager_v1.croncat.testnet get_slot_ids
     /// ```
     pub fn get_slot_ids(&self) -> Vec<U128> {
         self.slots
-            .to_vec()
             .iter()
-            .map(|i| U128::from(i.0))
+            .map(|(slot, _)| U128::from(slot))
+            .collect()
+    }
+
+    /// Gets the slots scheduled between two timestamps (NANOS), with their tasks
+    /// Used to render the upcoming load & plan agent capacity
+    ///
+    /// Optional Parameters:
+    /// "from_ts" - First timestamp included, defaults to the current slot.
+    /// "to_ts" - Last timestamp included, defaults to no end.
+    /// "limit" - Number of slots returned, defaults to 10 & at most SLOTS_RANGE_MAX.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_slots_range '{"from_ts": "1633759440000000000", "to_ts": "1633763040000000000", "limit": 20}'
+    /// ```
+    pub fn get_slots_range(
+        &self,
+        from_ts: Option<U64>,
+        to_ts: Option<U64>,
+        limit: Option<U64>,
+    ) -> Vec<SlotSummary> {
+        let from = from_ts
+            .map(|ts| u128::from(ts.0))
+            .unwrap_or_else(|| self.get_slot_id(None));
+        let to = match to_ts {
+            Some(ts) => {
+                assert!(u128::from(ts.0) >= from, "Range must end after it starts");
+                Bound::Included(u128::from(ts.0))
+            }
+            None => Bound::Unbounded,
+        };
+        let limit = u64::min(limit.map(|l| l.0).unwrap_or(10), SLOTS_RANGE_MAX);
+
+        self.slots
+            .range((Bound::Included(from), to))
+            .take(limit as usize)
+            .map(|(slot, slot_tasks)| SlotSummary {
+                slot: U128::from(slot),
+                task_count: U64::from(slot_tasks.len()),
+                task_hashes: slot_tasks.iter().map(Base64VecU8::from).collect(),
+            })
             .collect()
     }
 
@@ -163,21 +489,14 @@ impl Contract {
         let mut ret: Vec<TaskHumanFriendly> = Vec::new();
         if let Some(U128(slot_number)) = slot {
             // User specified a slot number, only return tasks in there.
-            let tasks_in_slot = self.slots.get(&slot_number).unwrap_or_default();
+            let tasks_in_slot = self
+                .slots
+                .get(&slot_number)
+                .map(|slot_tasks| slot_tasks.to_vec())
+                .unwrap_or_default();
             for task_hash in tasks_in_slot.iter() {
-                let task = self.tasks.get(&task_hash).expect("No task found by hash");
-                ret.push(TaskHumanFriendly {
-                    owner_id: task.owner_id.clone(),
-                    contract_id: task.contract_id.clone(),
-                    function_id: task.function_id.clone(),
-                    cadence: task.cadence.clone(),
-                    recurring: task.recurring,
-                    total_deposit: task.total_deposit,
-                    deposit: task.deposit,
-                    gas: task.gas,
-                    arguments: task.arguments.clone(),
-                    hash: Base64VecU8::from(task_hash.clone()),
-                });
+                let task = self.tasks.get(task_hash).expect("No task found by hash");
+                ret.push(TaskHumanFriendly::from((task_hash.clone(), task)));
             }
         } else {
             let mut start = 0;
@@ -194,18 +513,7 @@ impl Contract {
             for i in start..end {
                 if let Some(task_hash) = keys.get(i) {
                     if let Some(task) = self.tasks.get(&task_hash) {
-                        ret.push(TaskHumanFriendly {
-                            owner_id: task.owner_id.clone(),
-                            contract_id: task.contract_id.clone(),
-                            function_id: task.function_id.clone(),
-                            cadence: task.cadence.clone(),
-                            recurring: task.recurring,
-                            total_deposit: task.total_deposit,
-                            deposit: task.deposit,
-                            gas: task.gas,
-                            arguments: task.arguments.clone(),
-                            hash: Base64VecU8::from(task_hash.clone()),
-                        });
+                        ret.push(TaskHumanFriendly::from((task_hash, task)));
                     }
                 }
             }
@@ -216,27 +524,86 @@ impl Contract {
     /// Returns tasks for a specific owner account
     ///
     /// ```bash
-    /// near view manager_v1.croncat.testnet get_tasks_by_owner '{"owner_id": "YOU.testnet"}'
+    /// near view manager_v1.croncat.testnet get_tasks_by_owner '{"owner_id": "YOU.testnet", "from_index": 0, "limit": 10}'
     /// ```
-    pub fn get_tasks_by_owner(&self, owner_id: AccountId) -> Vec<TaskHumanFriendly> {
+    pub fn get_tasks_by_owner(
+        &self,
+        owner_id: AccountId,
+        from_index: Option<U64>,
+        limit: Option<U64>,
+    ) -> Vec<TaskHumanFriendly> {
         let mut ret: Vec<TaskHumanFriendly> = Vec::new();
+        let owner_tasks = match self.task_owners.get(&owner_id) {
+            Some(owner_tasks) => owner_tasks,
+            None => return ret,
+        };
+        let start = from_index.map(|i| i.0).unwrap_or(0);
+        let limit = limit.map(|l| l.0).unwrap_or(10);
+        let end = u64::min(start.saturating_add(limit), owner_tasks.len());
 
-        // User specified a slot number, only return tasks in there.
-        let owner_tasks = self.task_owners.get(&owner_id).expect("No owner tasks");
-        for task_hash in owner_tasks.iter() {
+        // Return owner tasks within range
+        let keys = owner_tasks.as_vector();
+        for i in start..end {
+            let task_hash = keys.get(i).expect("No owner task at index");
             let task = self.tasks.get(&task_hash).expect("No task found by hash");
-            ret.push(TaskHumanFriendly {
-                owner_id: task.owner_id.clone(),
-                contract_id: task.contract_id.clone(),
-                function_id: task.function_id.clone(),
-                cadence: task.cadence.clone(),
-                recurring: task.recurring,
-                total_deposit: task.total_deposit,
-                deposit: task.deposit,
-                gas: task.gas,
-                arguments: task.arguments.clone(),
-                hash: Base64VecU8::from(task_hash.clone()),
-            });
+            ret.push(TaskHumanFriendly::from((task_hash, task)));
+        }
+
+        ret
+    }
+
+    /// Returns tasks matching all of the given filters, like every task calling a contract
+    /// Pages over the tasks calling "contract_id" when given, otherwise over all tasks,
+    /// so a page can hold less than "limit" matching tasks.
+    ///
+    /// Optional Parameters:
+    /// "contract_id" - Only tasks calling this contract, uses the target contract index.
+    /// "function_id" - Only tasks calling this method.
+    /// "recurring" - Only recurring or only one-off tasks.
+    /// "min_total_deposit" - Only tasks with at least this balance left.
+    /// "max_total_deposit" - Only tasks with at most this balance left.
+    /// "next_run_before" - Only scheduled tasks with a next slot before this timestamp (NANOS), paused tasks excluded.
+    /// "from_index" & "limit" - Page of tasks to scan, defaults to the first 10.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_tasks_filtered '{"contract_id": "counter.in.testnet", "recurring": true, "from_index": 0, "limit": 10}'
+    /// ```
+    pub fn get_tasks_filtered(
+        &self,
+        contract_id: Option<AccountId>,
+        function_id: Option<String>,
+        recurring: Option<bool>,
+        min_total_deposit: Option<U128>,
+        max_total_deposit: Option<U128>,
+        next_run_before: Option<U64>,
+        from_index: Option<U64>,
+        limit: Option<U64>,
+    ) -> Vec<TaskHumanFriendly> {
+        let mut ret: Vec<TaskHumanFriendly> = Vec::new();
+        let start = from_index.map(|i| i.0).unwrap_or(0);
+        let limit = limit.map(|l| l.0).unwrap_or(10);
+
+        // Narrow down to the tasks calling a contract when possible
+        let contract_tasks = contract_id.map(|id| self.task_contracts.get(&id));
+        let keys = match &contract_tasks {
+            Some(Some(contract_tasks)) => contract_tasks.as_vector(),
+            Some(None) => return ret,
+            None => self.tasks.keys_as_vector(),
+        };
+        let end = u64::min(start.saturating_add(limit), keys.len());
+
+        for i in start..end {
+            let task_hash = keys.get(i).expect("No task at index");
+            let task = self.tasks.get(&task_hash).expect("No task found by hash");
+            if function_id.as_ref().is_some_and(|f| f != &task.function_id)
+                || recurring.is_some_and(|r| r != task.recurring)
+                || min_total_deposit.is_some_and(|min| task.total_deposit.0 < min.0)
+                || max_total_deposit.is_some_and(|max| task.total_deposit.0 > max.0)
+                || next_run_before.is_some_and(|ts| task.paused || task.slot.0 >= u128::from(ts.0))
+            {
+                continue;
+            }
+            ret.push(TaskHumanFriendly::from((task_hash, task)));
         }
 
         ret
@@ -248,21 +615,10 @@ impl Contract {
     /// near view manager_v1.croncat.testnet get_task '{"task_hash": "r2Jv…T4U4="}'
     /// ```
     pub fn get_task(&self, task_hash: Base64VecU8) -> TaskHumanFriendly {
-        let hash = task_hash.clone().0;
+        let hash = task_hash.0;
         let task = self.tasks.get(&hash).expect("No task found by hash");
 
-        TaskHumanFriendly {
-            owner_id: task.owner_id.clone(),
-            contract_id: task.contract_id.clone(),
-            function_id: task.function_id.clone(),
-            cadence: task.cadence.clone(),
-            recurring: task.recurring,
-            total_deposit: task.total_deposit,
-            deposit: task.deposit,
-            gas: task.gas,
-            arguments: task.arguments.clone(),
-            hash: task_hash,
-        }
+        TaskHumanFriendly::from((hash, task))
     }
 
     /// Get the hash of a task based on parameters
@@ -286,6 +642,111 @@ impl Contract {
         Base64VecU8::from(env::sha256(input.as_bytes()))
     }
 
+    /// Reports slot index inconsistencies for a page of slots & tasks, without fixing them.
+    /// Returns slot entries with no matching task, and tasks missing from their slot.
+    /// See `audit_slots` for repairing them.
+    ///
+    /// Optional Parameters:
+    /// "from_slot" - Last slot id of the previous page, only the slots after it are walked.
+    /// "from_index" - Index of the first task to check.
+    /// "limit" - Amount of slots & tasks to check, defaults to 10.
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_slot_inconsistencies '{"from_slot": "1633759440000000000", "from_index": "0", "limit": "100"}'
+    /// ```
+    pub fn get_slot_inconsistencies(
+        &self,
+        from_slot: Option<U128>,
+        from_index: Option<U64>,
+        limit: Option<U64>,
+    ) -> (Vec<(U128, Base64VecU8)>, Vec<Base64VecU8>) {
+        let (stale, unscheduled) = self.find_slot_inconsistencies(from_slot, from_index, limit);
+        (
+            stale
+                .into_iter()
+                .map(|(slot, hash)| (U128::from(slot), Base64VecU8::from(hash)))
+                .collect(),
+            unscheduled.into_iter().map(Base64VecU8::from).collect(),
+        )
+    }
+
+    /// Walks `limit` slots (in order) after `from_slot`, and `limit` tasks (by index) starting at `from_index`.
+    /// - Stale: slot entries whose task is gone, or whose task is scheduled in another slot
+    /// - Unscheduled: tasks that cannot be found in the slot they are scheduled in,
+    ///   except tasks just executed, as their callback is still to reschedule them
+    pub(crate) fn find_slot_inconsistencies(
+        &self,
+        from_slot: Option<U128>,
+        from_index: Option<U64>,
+        limit: Option<U64>,
+    ) -> SlotInconsistencies {
+        let start = from_index.map(|i| i.0).unwrap_or(0);
+        let limit = limit.map(|l| l.0).unwrap_or(10);
+
+        // Page slots by key, so slots added or removed between pages do not shift the page
+        let slots: Vec<(u128, Vector<Vec<u8>>)> = match from_slot {
+            Some(from_slot) => self
+                .slots
+                .iter_from(from_slot.0)
+                .take(limit as usize)
+                .collect(),
+            None => self.slots.iter().take(limit as usize).collect(),
+        };
+
+        let mut stale: Vec<(u128, Vec<u8>)> = Vec::new();
+        for (slot, slot_tasks) in slots {
+            for task_hash in slot_tasks.iter() {
+                let scheduled = self
+                    .tasks
+                    .get(&task_hash)
+                    .map(|task| task.slot.0 == slot)
+                    .unwrap_or(false);
+                if !scheduled {
+                    stale.push((slot, task_hash));
+                }
+            }
+        }
+
+        let mut unscheduled: Vec<Vec<u8>> = Vec::new();
+        let keys = self.tasks.keys_as_vector();
+        let end = u64::min(start.saturating_add(limit), keys.len());
+        for i in start..end {
+            if let Some(task_hash) = keys.get(i) {
+                if let Some(task) = self.tasks.get(&task_hash).filter(|t| !t.paused) {
+                    let in_flight = task.last_executed.0 > 0
+                        && env::block_timestamp() < task.last_executed.0 + CALLBACK_MAX_DELAY;
+                    if in_flight {
+                        continue;
+                    }
+                    let in_slot = self
+                        .slots
+                        .get(&task.slot.0)
+                        .map(|slot_tasks| slot_tasks.iter().any(|h| h == task_hash))
+                        .unwrap_or(false);
+                    if !in_slot {
+                        unscheduled.push(task_hash);
+                    }
+                }
+            }
+        }
+
+        (stale, unscheduled)
+    }
+
+    /// Gets the targets tasks & triggers can call
+    /// Returns (denied targets, allowed targets, allow list enforced)
+    ///
+    /// ```bash
+    /// near view manager_v1.croncat.testnet get_targets
+    /// ```
+    pub fn get_targets(&self) -> (Vec<String>, Vec<String>, bool) {
+        (
+            self.denied_targets.to_vec(),
+            self.allowed_targets.to_vec(),
+            self.allowed_targets_only,
+        )
+    }
+
     /// Gets list of agent ids
     ///
     /// ```bash
@@ -333,12 +794,8 @@ impl Contract {
 
             // Get slot total to test agent in slot
             // get task based on current slot, priority goes to tasks that have fallen behind (using floor key)
-            let (slot_opt, slot_ballpark) = if let Some(k) = self.slots.floor_key(&current_slot) {
-                (self.slots.get(&k), k)
-            } else {
-                (self.slots.get(&current_slot), current_slot)
-            };
-            let slot_data = slot_opt.unwrap_or_default();
+            let slot_ballpark = self.get_slot_ballpark(current_slot);
+            let slot_len = self.get_slot_task_count(slot_ballpark);
 
             // // Otherwise, assess if they are in active set, or are able to cover an agent that missed previous slot
             // let (can_execute, _, agent_tasks) =
@@ -349,8 +806,8 @@ impl Contract {
 
             // Otherwise, assess if they are in active set, or are able to cover an agent that missed previous slot
             let (can_execute, _, agent_tasks) =
-                self.check_agent_can_execute(account_id.to_string(), slot_data.len() as u64);
-            if !can_execute && slot_ballpark == current_slot {
+                self.check_agent_can_execute(account_id.to_string(), slot_len);
+            if !can_execute && slot_ballpark >= current_slot {
                 return empty;
             }
 
@@ -379,7 +836,7 @@ impl Contract {
     ) -> (bool, u64, u64) {
         // get the index this agent
         let index_raw = self.agent_active_queue.iter().position(|x| x == account_id);
-        let active_index = self.agent_active_index as u64;
+        let active_index = self.agent_active_index;
         let agents_total = self.agent_active_queue.len();
         let mut index: u64 = 0;
 
@@ -450,12 +907,13 @@ impl Contract {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{storage_fee, TaskArgs};
     use near_sdk::json_types::ValidAccountId;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::{testing_env, MockedBlockchain};
 
     const BLOCK_START_TS: u64 = 1_624_151_503_447_000_000;
-    const AGENT_STORAGE_FEE: u128 = 2260000000000000000000;
+    const AGENT_STORAGE_FEE: u128 = 2980000000000000000000;
 
     fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -478,37 +936,240 @@ mod tests {
     }
 
     #[test]
-    fn test_task_get_only_active() {
+    fn test_preview_cadence() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+
+        // Every day at 09:00 New York time, which is 13:00 UTC in summer
+        let preview = contract.preview_cadence(
+            "0 0 9 * * *".to_string(),
+            Some(2),
+            None,
+            Some("America/New_York".to_string()),
+        );
+        assert_eq!(preview.error, None);
+        assert_eq!(
+            preview.timestamps,
+            vec![
+                U64::from(1624194000000000000),
+                U64::from(1624280400000000000)
+            ]
+        );
+        assert_eq!(
+            preview.slots,
+            vec![
+                U128::from(1624194060000000000),
+                U128::from(1624280460000000000)
+            ]
+        );
+
+        // Stops when a schedule has no more occurrences
+        let preview = contract.preview_cadence(
+            "0 0 0 1 7 * 2021".to_string(),
+            Some(5),
+            Some(U64::from(BLOCK_START_TS)),
+            None,
+        );
+        assert_eq!(preview.timestamps, vec![U64::from(1625097600000000000)]);
+        assert_eq!(preview.slots.len(), 1);
+    }
+
+    #[test]
+    fn test_estimate_task_cost() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        let cost: u128 = 500000000020000000100;
+        let estimate = |executions: Option<u64>, duration: Option<u64>, total: Option<u128>| {
+            contract.estimate_task_cost(
+                "0 0 * * * *".to_string(),
+                Some(true),
+                Some(U128::from(100)),
+                Some(200),
+                None,
+                None,
+                None,
+                executions.map(U64::from),
+                duration.map(U64::from),
+                total.map(U128::from),
+            )
+        };
+
+        let storage = storage_fee(&contract);
+        let estimate1 = estimate(Some(10), None, Some(cost * 5 + 1 + storage));
+        assert_eq!(estimate1.cost_per_execution.0, cost);
+        assert_eq!(estimate1.minimum_deposit.0, cost * 2 + storage);
+        assert_eq!(estimate1.executions, Some(U64::from(10)));
+        assert_eq!(
+            estimate1.required_deposit,
+            Some(U128::from(cost * 10 + storage))
+        );
+        assert_eq!(estimate1.executions_funded, Some(U64::from(5)));
+        // Hourly from 01:11 UTC, the 5th execution is at 06:00 UTC
+        assert_eq!(estimate1.depletion_ts, Some(U64::from(1624168800000000000)));
+
+        // One day of hourly executions
+        let estimate2 = estimate(None, Some(24 * 3600 * NANO), None);
+        assert_eq!(estimate2.executions, Some(U64::from(24)));
+        assert_eq!(estimate2.executions_funded, None);
+        assert_eq!(estimate2.depletion_ts, None);
+
+        // Extrapolated past the scanned occurrences
+        let estimate3 = estimate(
+            None,
+            Some(30 * 24 * 3600 * NANO),
+            Some(cost * 400 + storage),
+        );
+        assert_eq!(estimate3.executions, Some(U64::from(720)));
+        assert_eq!(estimate3.executions_funded, Some(U64::from(400)));
+        assert_eq!(estimate3.depletion_ts, Some(U64::from(1625590096553000000)));
+    }
+
+    #[test]
+    fn test_estimate_task_cost_not_recurring() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        let estimate = contract.estimate_task_cost(
+            "0 0 * * * *".to_string(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(U128::from(ONE_NEAR)),
+        );
+        assert_eq!(
+            estimate.minimum_deposit.0,
+            estimate.cost_per_execution.0 + storage_fee(&contract)
+        );
+        assert_eq!(estimate.executions, None);
+        assert_eq!(estimate.executions_funded, Some(U64::from(1)));
+        assert_eq!(estimate.depletion_ts, Some(U64::from(1624154400000000000)));
+    }
+
+    #[test]
+    fn test_estimate_task_cost_minimum_deposit_creates_task() {
         let mut context = get_context(accounts(1));
         testing_env!(context.build());
         let mut contract = Contract::new();
+        let arguments = Base64VecU8::from(b"{\"amount\": \"10\"}".to_vec());
+        testing_env!(context.is_view(true).build());
+        let estimate = contract.estimate_task_cost(
+            "0 0 * * * *".to_string(),
+            Some(true),
+            Some(U128::from(100)),
+            Some(200),
+            Some(arguments.clone()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
 
-        // Move forward time and blocks to get more accurate bps
+        // Attaching exactly the minimum is enough
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
-            .block_timestamp(BLOCK_START_TS + (6 * NANO))
+            .attached_deposit(estimate.minimum_deposit.0)
             .build());
+        let task_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(100)),
+            gas: Some(200),
+            arguments: Some(arguments.clone()),
+            ..TaskArgs::new(accounts(3), "increment", "0 0 * * * *")
+        });
+        let task = contract.tasks.get(&task_hash.0).unwrap();
+        assert_eq!(task.total_deposit.0, estimate.cost_per_execution.0 * 2);
+        assert_eq!(
+            task.storage_deposit.0,
+            contract.task_storage_fee(&arguments)
+        );
+    }
 
-        // create a some tasks
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/10 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
+    #[test]
+    #[should_panic(expected = "Cadence string invalid")]
+    fn test_estimate_task_cost_bad_cadence() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        contract.estimate_task_cost(
+            "0 0 25 * * *".to_string(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
             None,
         );
-        contract.create_task(
-            accounts(3),
-            "decrement".to_string(),
-            "*/10 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
+    }
+
+    #[test]
+    fn test_preview_cadence_errors() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        testing_env!(context.is_view(true).build());
+        let preview = contract.preview_cadence("0 0 25 * * *".to_string(), None, None, None);
+        assert_eq!(
+            preview.error,
+            Some(
+                "Cadence string invalid: Invalid expression: Invalid cron expression.".to_string()
+            )
+        );
+        assert!(preview.timestamps.is_empty());
+
+        let preview = contract.preview_cadence(
+            "0 0 9 * * *".to_string(),
             None,
+            None,
+            Some("Mars/Olympus".to_string()),
         );
+        assert_eq!(
+            preview.error,
+            Some("Timezone invalid: Mars/Olympus".to_string())
+        );
+        assert!(preview.slots.is_empty());
+    }
+
+    #[test]
+    fn test_task_get_only_active() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+
+        // Move forward time and blocks to get more accurate bps
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .block_timestamp(BLOCK_START_TS + (6 * NANO))
+            .build());
+
+        // create a some tasks
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/10 * * * * *")
+        });
         testing_env!(context
             .is_view(false)
             .block_timestamp(BLOCK_START_TS + (120 * NANO))
@@ -525,6 +1186,264 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_tasks_by_owner_paginated() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+
+        for function_id in ["increment", "decrement", "reset"].iter() {
+            contract.create_task_with(TaskArgs {
+                recurring: Some(false),
+                deposit: Some(U128::from(0)),
+                gas: Some(200),
+                ..TaskArgs::new(accounts(3), function_id, "*/10 * * * * *")
+            });
+        }
+        testing_env!(context.is_view(true).build());
+
+        let owner_id = accounts(1).to_string();
+        assert_eq!(
+            contract
+                .get_tasks_by_owner(owner_id.clone(), None, None)
+                .len(),
+            3
+        );
+        let first_page = contract.get_tasks_by_owner(owner_id.clone(), None, Some(U64(2)));
+        let last_page = contract.get_tasks_by_owner(owner_id.clone(), Some(U64(2)), Some(U64(2)));
+        assert_eq!(first_page.len(), 2);
+        assert_eq!(last_page.len(), 1);
+        assert_eq!(last_page[0].function_id, "reset".to_string());
+        assert!(contract
+            .get_tasks_by_owner(owner_id, Some(U64(5)), None)
+            .is_empty());
+
+        // Owners without tasks get an empty page instead of a panic
+        assert!(contract
+            .get_tasks_by_owner(accounts(2).to_string(), None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_tasks_filtered() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(2000000000040000000200 + storage_fee(&contract))
+            .build());
+        let recurring_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(true),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/10 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/10 * * * * *")
+        });
+        testing_env!(context
+            .attached_deposit(3000000000000000000000 + storage_fee(&contract))
+            .build());
+        let hourly_hash = contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(4), "increment", "0 0 */1 * * *")
+        });
+
+        let filtered = |contract: &Contract,
+                        contract_id: Option<ValidAccountId>,
+                        function_id: Option<&str>,
+                        recurring: Option<bool>,
+                        min_total_deposit: Option<u128>,
+                        max_total_deposit: Option<u128>,
+                        next_run_before: Option<u64>| {
+            contract
+                .get_tasks_filtered(
+                    contract_id.map(|id| id.to_string()),
+                    function_id.map(|f| f.to_string()),
+                    recurring,
+                    min_total_deposit.map(U128),
+                    max_total_deposit.map(U128),
+                    next_run_before.map(U64),
+                    None,
+                    None,
+                )
+                .len()
+        };
+        assert_eq!(filtered(&contract, None, None, None, None, None, None), 3);
+        assert_eq!(
+            filtered(&contract, Some(accounts(3)), None, None, None, None, None),
+            2
+        );
+        assert_eq!(
+            filtered(&contract, Some(accounts(4)), None, None, None, None, None),
+            1
+        );
+        assert_eq!(
+            filtered(&contract, Some(accounts(5)), None, None, None, None, None),
+            0
+        );
+        assert_eq!(
+            filtered(&contract, None, Some("increment"), None, None, None, None),
+            2
+        );
+        assert_eq!(
+            filtered(
+                &contract,
+                Some(accounts(3)),
+                Some("decrement"),
+                None,
+                None,
+                None,
+                None
+            ),
+            1
+        );
+        assert_eq!(
+            filtered(&contract, None, None, Some(true), None, None, None),
+            1
+        );
+        assert_eq!(
+            filtered(
+                &contract,
+                None,
+                None,
+                None,
+                Some(2500000000000000000000),
+                None,
+                None
+            ),
+            1
+        );
+        assert_eq!(
+            filtered(
+                &contract,
+                None,
+                None,
+                None,
+                None,
+                Some(2100000000000000000000),
+                None
+            ),
+            2
+        );
+        let ten_minutes = BLOCK_START_TS + 600 * NANO;
+        assert_eq!(
+            filtered(&contract, None, None, None, None, None, Some(ten_minutes)),
+            2
+        );
+        assert_eq!(
+            contract
+                .get_tasks_filtered(
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(U64(1)),
+                    Some(U64(1))
+                )
+                .len(),
+            1
+        );
+
+        // Paused tasks have no next run
+        contract.pause_task(recurring_hash);
+        assert_eq!(
+            filtered(&contract, None, None, None, None, None, Some(ten_minutes)),
+            1
+        );
+
+        // Removed tasks leave the target contract index
+        contract.remove_task(hourly_hash);
+        assert!(contract
+            .task_contracts
+            .get(&accounts(4).to_string())
+            .is_none());
+        assert_eq!(
+            filtered(&contract, Some(accounts(4)), None, None, None, None, None),
+            0
+        );
+    }
+
+    #[test]
+    fn test_get_slots_range() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .is_view(false)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
+            .build());
+        for (function_id, cadence) in [
+            ("increment", "*/10 * * * * *"),
+            ("decrement", "*/10 * * * * *"),
+            ("increment", "0 0 */1 * * *"),
+        ]
+        .iter()
+        {
+            contract.create_task_with(TaskArgs {
+                recurring: Some(false),
+                deposit: Some(U128::from(0)),
+                gas: Some(200),
+                ..TaskArgs::new(accounts(3), function_id, cadence)
+            });
+        }
+        testing_env!(context.is_view(true).build());
+
+        let slots = contract.get_slots_range(None, None, None);
+        assert_eq!(slots.len(), 2);
+        assert!(slots[0].slot.0 < slots[1].slot.0);
+        assert_eq!(slots[0].task_count, U64(2));
+        assert_eq!(slots[0].task_hashes.len(), 2);
+        assert_eq!(slots[1].task_count, U64(1));
+        assert_eq!(
+            slots[1].task_hashes,
+            contract
+                .get_tasks(Some(slots[1].slot), None, None)
+                .into_iter()
+                .map(|t| t.hash)
+                .collect::<Vec<Base64VecU8>>()
+        );
+
+        // Bounded by timestamps & limit
+        let ten_minutes = U64(BLOCK_START_TS + 600 * NANO);
+        assert_eq!(
+            contract
+                .get_slots_range(None, Some(ten_minutes), None)
+                .len(),
+            1
+        );
+        assert_eq!(contract.get_slots_range(None, None, Some(U64(1))).len(), 1);
+        let after_first = U64(slots[0].slot.0 as u64 + 1);
+        let later = contract.get_slots_range(Some(after_first), None, None);
+        assert_eq!(later.len(), 1);
+        assert_eq!(later[0].slot, slots[1].slot);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range must end after it starts")]
+    fn test_get_slots_range_invalid() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let contract = Contract::new();
+        contract.get_slots_range(
+            Some(U64(BLOCK_START_TS)),
+            Some(U64(BLOCK_START_TS - 1)),
+            None,
+        );
+    }
+
     // 1 agent, always
     #[test]
     fn test_check_agent_can_execute_single_agent() {
@@ -535,29 +1454,23 @@ mod tests {
         // Move forward time and blocks to get more accurate bps
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
             .block_timestamp(BLOCK_START_TS)
             .build());
 
         // create a some tasks
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "decrement".to_string(),
-            "*/2 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/1 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/2 * * * * *")
+        });
 
         // Register an agent
         testing_env!(context
@@ -572,7 +1485,7 @@ mod tests {
             .predecessor_account_id(accounts(4))
             .build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec, true, "Can execute: Single Agent: True");
+        assert!(can_exec, "Can execute: Single Agent: True");
         assert_eq!(index, 0, "Can execute: Single Agent: Index 0");
         testing_env!(context
             .is_view(false)
@@ -580,7 +1493,7 @@ mod tests {
             .predecessor_account_id(accounts(4))
             .build());
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec_2, true, "Can execute: Single Agent: True");
+        assert!(can_exec_2, "Can execute: Single Agent: True");
         assert_eq!(index_2, 0, "Can execute: Single Agent: Index 0");
     }
 
@@ -593,47 +1506,35 @@ mod tests {
         // Move forward time and blocks to get more accurate bps
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
             .block_timestamp(BLOCK_START_TS)
             .build());
 
         // create a some tasks
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/2 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/3 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/4 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/1 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/2 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/3 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/4 * * * * *")
+        });
         testing_env!(context
             .is_view(false)
             .block_timestamp(BLOCK_START_TS + (120 * NANO))
@@ -655,10 +1556,10 @@ mod tests {
         contract.tick();
         testing_env!(context.is_view(true).build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec, true, "Can execute: Multi Agent: True");
+        assert!(can_exec, "Can execute: Multi Agent: True");
         assert_eq!(index, 0, "Can execute: Multi Agent: Index 0");
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(5).to_string(), 1);
-        assert_eq!(can_exec_2, false, "Can execute: Multi Agent: False");
+        assert!(!can_exec_2, "Can execute: Multi Agent: False");
         assert_eq!(index_2, 1, "Can execute: Multi Agent: Index 0");
 
         // active index shift
@@ -669,10 +1570,10 @@ mod tests {
         contract.agent_active_index = 1;
         testing_env!(context.is_view(true).build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec, false, "Can execute: Multi Agent: True");
+        assert!(!can_exec, "Can execute: Multi Agent: True");
         assert_eq!(index, 0, "Can execute: Multi Agent: Index 0");
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(5).to_string(), 1);
-        assert_eq!(can_exec_2, true, "Can execute: Multi Agent: False");
+        assert!(can_exec_2, "Can execute: Multi Agent: False");
         assert_eq!(index_2, 1, "Can execute: Multi Agent: Index 0");
     }
 
@@ -689,47 +1590,35 @@ mod tests {
         // Move forward time and blocks to get more accurate bps
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
             .block_timestamp(BLOCK_START_TS)
             .build());
 
         // create a some tasks
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "decrement".to_string(),
-            "*/2 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/3 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "decrement".to_string(),
-            "*/4 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/1 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/2 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/3 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/4 * * * * *")
+        });
         testing_env!(context
             .is_view(false)
             .block_timestamp(BLOCK_START_TS + (120 * NANO))
@@ -751,11 +1640,11 @@ mod tests {
         contract.tick();
         // testing_env!(context.is_view(true).build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec, true, "Can execute: Multi Agent: True");
+        assert!(can_exec, "Can execute: Multi Agent: True");
         assert_eq!(index, 0, "Can execute: Multi Agent: Index 0");
         contract.agent_active_index = 1;
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(5).to_string(), 1);
-        assert_eq!(can_exec_2, true, "Can execute: Multi Agent: True");
+        assert!(can_exec_2, "Can execute: Multi Agent: True");
         assert_eq!(index_2, 1, "Can execute: Multi Agent: Index 0");
 
         // active index shift
@@ -766,11 +1655,11 @@ mod tests {
         contract.agent_active_index = 0;
         // testing_env!(context.is_view(true).build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec, true, "Can execute: Multi Agent: True");
+        assert!(can_exec, "Can execute: Multi Agent: True");
         assert_eq!(index, 0, "Can execute: Multi Agent: Index 0");
         contract.agent_active_index = 1;
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(5).to_string(), 1);
-        assert_eq!(can_exec_2, true, "Can execute: Multi Agent: True");
+        assert!(can_exec_2, "Can execute: Multi Agent: True");
         assert_eq!(index_2, 1, "Can execute: Multi Agent: Index 0");
     }
 
@@ -787,47 +1676,35 @@ mod tests {
         // Move forward time and blocks to get more accurate bps
         testing_env!(context
             .is_view(false)
-            .attached_deposit(1000000000020000000100)
+            .attached_deposit(1000000000020000000100 + storage_fee(&contract))
             .block_timestamp(BLOCK_START_TS)
             .build());
 
         // create a some tasks
-        contract.create_task(
-            accounts(3),
-            "increment".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "decrement".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        );
-        contract.create_task(
-            accounts(3),
-            "excrement".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        ); // #poojokes
-        contract.create_task(
-            accounts(3),
-            "excitement".to_string(),
-            "*/1 * * * * *".to_string(),
-            Some(false),
-            Some(U128::from(0)),
-            Some(200),
-            None,
-        ); // #poojokes
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "increment", "*/1 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "decrement", "*/1 * * * * *")
+        });
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "excrement", "*/1 * * * * *")
+        }); // #poojokes
+        contract.create_task_with(TaskArgs {
+            recurring: Some(false),
+            deposit: Some(U128::from(0)),
+            gas: Some(200),
+            ..TaskArgs::new(accounts(3), "excitement", "*/1 * * * * *")
+        }); // #poojokes
         testing_env!(context
             .is_view(false)
             .block_timestamp(BLOCK_START_TS + (120 * NANO))
@@ -849,25 +1726,25 @@ mod tests {
         contract.tick();
         // testing_env!(context.is_view(true).build());
         let (can_exec, index, _) = contract.check_agent_can_execute(accounts(4).to_string(), 3);
-        assert_eq!(can_exec, true, "Can execute: Multi Agent: True");
+        assert!(can_exec, "Can execute: Multi Agent: True");
         assert_eq!(index, 0, "Can execute: Multi Agent: Index 0");
         contract.agent_active_index = 1;
         let (can_exec_2, index_2, _) = contract.check_agent_can_execute(accounts(5).to_string(), 2);
-        assert_eq!(can_exec_2, true, "Can execute: Multi Agent: True");
+        assert!(can_exec_2, "Can execute: Multi Agent: True");
         assert_eq!(index_2, 1, "Can execute: Multi Agent: Index 1");
         contract.agent_active_index = 0;
         let (can_exec_3, index_3, _) = contract.check_agent_can_execute(accounts(4).to_string(), 1);
-        assert_eq!(can_exec_3, true, "Can execute: Multi Agent: True");
+        assert!(can_exec_3, "Can execute: Multi Agent: True");
         assert_eq!(index_3, 0, "Can execute: Multi Agent: Index 0");
         let (can_exec_4, index_4, _) = contract.check_agent_can_execute(accounts(5).to_string(), 1);
-        assert_eq!(can_exec_4, false, "Can execute: Multi Agent: False");
+        assert!(!can_exec_4, "Can execute: Multi Agent: False");
         assert_eq!(index_4, 1, "Can execute: Multi Agent: Index 1");
         contract.agent_active_index = 1;
         let (can_exec_5, index_5, _) = contract.check_agent_can_execute(accounts(4).to_string(), 0);
-        assert_eq!(can_exec_5, false, "Can execute: Multi Agent: True");
+        assert!(!can_exec_5, "Can execute: Multi Agent: True");
         assert_eq!(index_5, 0, "Can execute: Multi Agent: Index 0");
         let (can_exec_6, index_6, _) = contract.check_agent_can_execute(accounts(5).to_string(), 0);
-        assert_eq!(can_exec_6, false, "Can execute: Multi Agent: False");
+        assert!(!can_exec_6, "Can execute: Multi Agent: False");
         assert_eq!(index_6, 1, "Can execute: Multi Agent: Index 1");
     }
 }
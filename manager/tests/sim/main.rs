@@ -3,9 +3,9 @@ mod test_utils;
 use crate::test_utils::{
     bootstrap_time_simulation, counter_create_task, find_log_from_outcomes, helper_create_task,
     sim_helper_create_agent_user, sim_helper_init, sim_helper_init_counter,
-    sim_helper_init_sputnikv2,
+    sim_helper_init_sputnikv2, sim_helper_init_staking_pool,
 };
-use manager::{Agent, TaskHumanFriendly};
+use manager::{Agent, CatchUpPolicy, Contribution, TaskHumanFriendly};
 use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json;
@@ -20,16 +20,18 @@ near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
     pub CRON_MANAGER_WASM_BYTES => "../target/wasm32-unknown-unknown/release/manager.wasm",
     pub COUNTER_WASM_BYTES => "../target/wasm32-unknown-unknown/release/rust_counter_tutorial.wasm",
     pub SPUTNIKV2_WASM_BYTES => "./tests/sputnik/sputnikdao2.wasm",
+    pub STAKING_POOL_WASM_BYTES => "../target/wasm32-unknown-unknown/release/staking_pool.wasm",
 }
 
 const MANAGER_ID: &str = "manager.sim";
 const COUNTER_ID: &str = "counter.sim";
 const SPUTNIKV2_ID: &str = "sputnikv2.sim";
+const STAKING_POOL_ID: &str = "pool.sim";
 const AGENT_ID: &str = "agent.sim";
 const USER_ID: &str = "user.sim";
 const NEW_NAME_ID: &str = "newname.sim";
 const TASK_BASE64: &str = "BBcr1GdY4iSMebFavu7yz4daPDDrlmxTf5ftC0RB8mQ=";
-const AGENT_REGISTRATION_COST: u128 = 2_260_000_000_000_000_000_000;
+const AGENT_REGISTRATION_COST: u128 = 2_980_000_000_000_000_000_000;
 const AGENT_FEE: u128 = 60_000_000_000_000_000_000_000u128;
 
 type TaskBase64Hash = String;
@@ -401,6 +403,22 @@ fn simulate_basic_task_checks() {
         deposit: U128::from(12000000000000),
         gas: 3000000000000,
         arguments: Base64VecU8::from(vec![]),
+        priority_tip: U128::from(0),
+        catch_up: CatchUpPolicy::Skip,
+        missed_occurrences: U64::from(0),
+        max_delay: None,
+        timezone: None,
+        low_balance_threshold: None,
+        low_balance_hook: None,
+        contributions: vec![Contribution {
+            account_id: COUNTER_ID.to_string(),
+            amount: U128::from(2_600_000_024_000_000_000_000u128),
+            storage_deposit: U128::from(0),
+        }],
+        pending_owner_id: None,
+        delegates: vec![],
+        paused: false,
+        gas_estimate: None,
         hash: Base64VecU8::from(vec![
             4, 23, 43, 212, 103, 88, 226, 36, 140, 121, 177, 90, 190, 238, 242, 207, 135, 90, 60,
             48, 235, 150, 108, 83, 127, 151, 237, 11, 68, 65, 242, 100,
@@ -536,6 +554,102 @@ fn simulate_basic_agent_registration_update() {
     assert_eq!(agent_result.payable_account_id, NEW_NAME_ID);
 }
 
+#[test]
+fn simulate_treasury_staking() {
+    let (root, cron) = sim_helper_init();
+    let pool = sim_helper_init_staking_pool(&root);
+
+    cron.call(
+        cron.account_id(),
+        "update_settings",
+        &json!({ "staking_pool_id": pool.account_id() })
+            .to_string()
+            .into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+
+    // Stake all of the surplus
+    let (_, _, _, surplus): (U128, U128, U128, U128) = root
+        .view(cron.account_id(), "get_balances", &[])
+        .unwrap_json();
+    assert!(surplus.0 > 0);
+    cron.call(
+        cron.account_id(),
+        "stake_surplus",
+        &json!({}).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+
+    let (_, _, staked, _): (U128, U128, U128, U128) = root
+        .view(cron.account_id(), "get_balances", &[])
+        .unwrap_json();
+    let pool_staked: U128 = root
+        .view(
+            pool.account_id(),
+            "get_account_staked_balance",
+            &json!({ "account_id": MANAGER_ID }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    // NOTE: Surplus shifts a bit with the gas spent by the owner call itself
+    assert!(staked.0 > 0);
+    assert_eq!(pool_staked.0, staked.0);
+
+    // The pool cannot change while it holds balance
+    let status = cron
+        .call(
+            cron.account_id(),
+            "update_settings",
+            &json!({ "staking_pool_id": USER_ID })
+                .to_string()
+                .into_bytes(),
+            DEFAULT_GAS,
+            0,
+        )
+        .status();
+    if let ExecutionStatus::Failure(err) = status {
+        assert!(err
+            .to_string()
+            .contains("Staked balance must be withdrawn before changing staking pool"));
+    } else {
+        panic!("Staking pool should not change while balance is staked.");
+    }
+
+    // Unstake & withdraw everything back
+    cron.call(
+        cron.account_id(),
+        "unstake",
+        &json!({ "amount": staked }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+    cron.call(
+        cron.account_id(),
+        "withdraw_stake",
+        &json!({ "amount": staked }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0,
+    )
+    .assert_success();
+
+    let (_, _, staked, _): (U128, U128, U128, U128) = root
+        .view(cron.account_id(), "get_balances", &[])
+        .unwrap_json();
+    let pool_unstaked: U128 = root
+        .view(
+            pool.account_id(),
+            "get_account_unstaked_balance",
+            &json!({ "account_id": MANAGER_ID }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(staked.0, 0);
+    assert_eq!(pool_unstaked.0, 0);
+}
+
 #[test]
 fn simulate_agent_unregister_check() {
     let (root, cron) = sim_helper_init();
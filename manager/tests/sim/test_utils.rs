@@ -1,9 +1,9 @@
 use crate::{
     TaskBase64Hash, AGENT_ID, COUNTER_ID, COUNTER_WASM_BYTES, CRON_MANAGER_WASM_BYTES, MANAGER_ID,
-    SPUTNIKV2_ID, SPUTNIKV2_WASM_BYTES, USER_ID,
+    SPUTNIKV2_ID, SPUTNIKV2_WASM_BYTES, STAKING_POOL_ID, STAKING_POOL_WASM_BYTES, USER_ID,
 };
 use near_primitives_core::account::Account as PrimitiveAccount;
-use near_sdk::json_types::Base64VecU8;
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde_json;
 use near_sdk::serde_json::json;
 use near_sdk_sim::account::AccessKey;
@@ -18,6 +18,14 @@ use std::cell::{RefCell, RefMut};
 use std::rc::Rc;
 
 pub(crate) fn helper_create_task(cron: &UserAccount, counter: &UserAccount) -> TaskBase64Hash {
+    // Task storage is paid on top of the task balance
+    let (storage_fee, _, _): (U128, U64, U64) = cron
+        .view(
+            cron.account_id(),
+            "get_task_storage_fee",
+            &json!({}).to_string().into_bytes(),
+        )
+        .unwrap_json();
     let execution_result = counter.call(
         cron.account_id(),
         "create_task",
@@ -32,7 +40,7 @@ pub(crate) fn helper_create_task(cron: &UserAccount, counter: &UserAccount) -> T
         .to_string()
         .into_bytes(),
         DEFAULT_GAS,
-        2_600_000_024_000_000_000_000u128, // deposit
+        2_600_000_024_000_000_000_000u128 + storage_fee.0, // deposit
     );
     execution_result.assert_success();
     let hash: Base64VecU8 = execution_result.unwrap_json();
@@ -100,6 +108,18 @@ pub(crate) fn sim_helper_init_sputnikv2(root_account: &UserAccount) -> UserAccou
     sputnik
 }
 
+pub(crate) fn sim_helper_init_staking_pool(root_account: &UserAccount) -> UserAccount {
+    // Deploy the mock staking pool and call "new" method
+    let pool = root_account.deploy(
+        &STAKING_POOL_WASM_BYTES,
+        STAKING_POOL_ID.into(),
+        STORAGE_AMOUNT,
+    );
+    pool.call(pool.account_id(), "new", &[], DEFAULT_GAS, 0)
+        .assert_success();
+    pool
+}
+
 pub(crate) fn counter_create_task(
     counter: &UserAccount,
     cron: AccountId,
@@ -1,3 +1,5 @@
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     collections::UnorderedSet,
@@ -198,7 +200,7 @@ impl Contract {
                     // Two paths:
                     // 1. automated claim via croncat manager
                     // 2. directly without manager, but has a task already
-                    if &owner_id == &self.cron_account_id {
+                    if owner_id == self.cron_account_id {
                         // Check that the task is the right function method
                         assert_eq!(
                             &task.contract_id,